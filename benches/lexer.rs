@@ -0,0 +1,60 @@
+//! Lexes a few megabytes of generated Lox and reports throughput. Run with `cargo bench`.
+
+use std::{hint::black_box, rc::Rc, time::Instant};
+
+use crafting_interpreters::Lexer;
+
+const TARGET_SIZE: usize = 4 * 1024 * 1024;
+const RUNS: u32 = 10;
+
+/// A mix of every kind of token, with a different identifier per chunk so nothing is too uniform.
+fn source() -> String {
+    let mut src = String::with_capacity(TARGET_SIZE + 1024);
+    let mut i = 0;
+
+    while src.len() < TARGET_SIZE {
+        src += &format!(
+            r#"/// Adds things up.
+class Counter_{i} < Base {{
+    init(start) {{ this.count = start; }}
+    bump(by) {{
+        /* block /* nested */ comment */
+        this.count = this.count + by * 0x1F - 2.5e-3;
+        return this.count >= 1_000_000 and !false;
+    }}
+}}
+var label_{i} = "counter \"{i}\"\n";
+for (var n = 0; n < {i}; n = n + 1) print Counter_{i}(n).bump(0b1010); // done
+"#
+        );
+        i += 1;
+    }
+
+    src
+}
+
+fn main() {
+    // The lexer shares its source with the tokens, so hand it an `Rc<str>` up front. From a `&str`
+    // it would copy the whole input on every run, and that copy would be timed along with lexing.
+    let src: Rc<str> = source().into();
+    let mb = src.len() as f64 / (1024.0 * 1024.0);
+
+    // Warm up, and make sure the input is actually valid.
+    let (tokens, errors) = Lexer::new(Rc::clone(&src)).parse();
+    assert!(errors.is_empty());
+
+    let started = Instant::now();
+
+    for _ in 0..RUNS {
+        black_box(Lexer::new(black_box(Rc::clone(&src))).count());
+    }
+
+    let secs = started.elapsed().as_secs_f64() / RUNS as f64;
+
+    println!(
+        "lexed {mb:.1} MiB into {} tokens in {:.1} ms ({:.0} MiB/s)",
+        tokens.len(),
+        secs * 1000.0,
+        mb / secs
+    );
+}
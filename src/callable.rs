@@ -0,0 +1,18 @@
+use crate::{
+    interpreter::{Interpreter, RuntimeError},
+    token::Token,
+    value::Value,
+};
+
+/// Anything that can sit on the left of a call expression.
+pub trait Callable {
+    fn arity(&self) -> usize;
+
+    /// `paren` is the call's closing parenthesis, which errors raised by the callee itself point at.
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError>;
+}
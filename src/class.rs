@@ -0,0 +1,116 @@
+use std::{cell::RefCell, collections::HashMap, fmt::Debug, rc::Rc};
+
+use crate::{
+    callable::Callable,
+    function::LoxFunction,
+    interpreter::{Interpreter, RuntimeError},
+    token::Token,
+    value::Value,
+};
+
+pub struct LoxClass {
+    name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: impl Into<String>,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            superclass,
+            methods,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Looks for `name` on this class first, then up the superclass chain.
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        match self.methods.get(name) {
+            Some(method) => Some(Rc::clone(method)),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+}
+
+impl Debug for LoxClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Implemented on the `Rc` so the instances a class creates can point back at it.
+impl Callable for Rc<LoxClass> {
+    fn arity(&self) -> usize {
+        self.find_method("init").map_or(0, |init| init.arity())
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let instance = Rc::new(RefCell::new(LoxInstance::new(Rc::clone(self))));
+
+        if let Some(init) = self.find_method("init") {
+            init.bind(Rc::clone(&instance))
+                .call(interpreter, paren, args)?;
+        }
+
+        Ok(Value::Instance(instance))
+    }
+}
+
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: HashMap<String, Value>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn class(&self) -> &LoxClass {
+        &self.class
+    }
+
+    /// Fields shadow methods. Methods come back bound to `instance` so `this` works when the
+    /// method is called later.
+    pub fn get(instance: &Rc<RefCell<Self>>, name: &Token) -> Result<Value, RuntimeError> {
+        let this = instance.borrow();
+
+        if let Some(value) = this.fields.get(name.lexeme()) {
+            return Ok(value.clone());
+        }
+
+        match this.class.find_method(name.lexeme()) {
+            Some(method) => Ok(Value::Function(Rc::new(method.bind(Rc::clone(instance))))),
+            None => Err(RuntimeError::new(
+                name.clone(),
+                format!("Undefined property '{}'.", name.lexeme()),
+            )),
+        }
+    }
+
+    pub fn set(&mut self, name: &Token, value: Value) {
+        self.fields.insert(name.lexeme().into(), value);
+    }
+}
+
+impl Debug for LoxInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
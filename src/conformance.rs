@@ -0,0 +1,203 @@
+//! Runs `.lox` files and checks them against expectations written in their comments. The format is
+//! the one the upstream Crafting Interpreters test suite uses:
+//!
+//! - `// expect: text` is the next line `print` should write.
+//! - `// expect runtime error: msg` means the program fails at runtime on this line.
+//! - `// Error at 'x': msg` is a static error on this line.
+//! - `// [line N] Error at 'x': msg` is a static error on line N. `[java line N]` means the same,
+//!   while `[c line N]` is meant for clox and ignored.
+//!
+//! Files containing `// nontest` are skipped.
+
+use std::{
+    cell::RefCell,
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crafting_interpreters::{Dialect, Interpreter, Lox, LoxError, Output};
+
+use crate::STACK_LIMIT;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Expectations {
+    pub output: Vec<String>,
+    /// Static errors in jlox's format, e.g. `[line 3] Error at 'x': Expect ';'.`.
+    pub errors: Vec<String>,
+    /// The line and message of the runtime error the program should stop with.
+    pub runtime_error: Option<(usize, String)>,
+}
+
+impl Expectations {
+    pub fn parse(src: &str) -> Self {
+        let mut expect = Self::default();
+
+        for (i, line) in src.lines().enumerate() {
+            // Like upstream, look for each annotation anywhere on the line rather than splitting at
+            // the first `//`, which may well be inside a string.
+            let after = |pattern: &str| line.find(pattern).map(|at| &line[at + pattern.len()..]);
+
+            if let Some(text) = after("// expect:") {
+                let text = text.strip_prefix(' ').unwrap_or(text);
+                expect.output.push(text.into());
+            } else if let Some(msg) = after("// expect runtime error: ") {
+                expect.runtime_error = Some((i + 1, msg.into()));
+            } else if let Some(msg) = after("// Error") {
+                expect.errors.push(format!("[line {}] Error{msg}", i + 1));
+            } else if let Some(rest) = after("// [line ").or_else(|| after("// [java line ")) {
+                expect.errors.push(format!("[line {rest}"));
+            }
+        }
+
+        expect
+    }
+}
+
+/// Runs one file, returning everything about it that didn't match its expectations.
+pub fn run_file(path: &Path, dialect: Dialect) -> io::Result<Vec<String>> {
+    let src = fs::read_to_string(path)?;
+    let expect = Expectations::parse(&src);
+
+    let output = Rc::new(RefCell::new(String::new()));
+    let mut interpreter =
+        Interpreter::new(Output::Buffer(Rc::clone(&output))).with_stack_limit(STACK_LIMIT);
+    let result = Lox::run(&mut interpreter, &src, dialect);
+
+    let mut failures = vec![];
+    let output = output.borrow();
+    let lines = output.lines().collect::<Vec<_>>();
+
+    for (i, expected) in expect.output.iter().enumerate() {
+        match lines.get(i) {
+            Some(line) if line == expected => {}
+            Some(line) => failures.push(format!("Expected output '{expected}', got '{line}'.")),
+            None => failures.push(format!("Missing expected output '{expected}'.")),
+        }
+    }
+
+    for line in lines.iter().skip(expect.output.len()) {
+        failures.push(format!("Got unexpected output '{line}'."));
+    }
+
+    let errors = match &result {
+        Err(err) => err.jlox_messages(),
+        Ok(_) => vec![],
+    };
+
+    for error in errors.iter() {
+        if !expect.errors.contains(error) {
+            failures.push(format!("Unexpected error: {error}"));
+        }
+    }
+
+    for error in expect.errors.iter() {
+        if !errors.contains(error) {
+            failures.push(format!("Missing expected error: {error}"));
+        }
+    }
+
+    match (&result, &expect.runtime_error) {
+        (Err(LoxError::Runtime(err)), Some((line, msg)))
+            if (err.token.line, &err.msg) != (*line, msg) =>
+        {
+            failures.push(format!(
+                "Expected runtime error '{msg}' on line {line}, got '{}' on line {}.",
+                err.msg, err.token.line
+            ));
+        }
+        (Err(LoxError::Runtime(_)), Some(_)) => {}
+        (Err(LoxError::Runtime(err)), None) => failures.push(format!(
+            "Unexpected runtime error '{}' on line {}.",
+            err.msg, err.token.line
+        )),
+        (_, Some((line, msg))) => {
+            failures.push(format!(
+                "Expected runtime error '{msg}' on line {line} and got none."
+            ));
+        }
+        _ => {}
+    }
+
+    Ok(failures)
+}
+
+/// Runs every `.lox` file under `dir`, printing the ones that fail and a summary. Returns whether
+/// they all passed.
+pub fn run_dir(dir: &Path, dialect: Dialect) -> io::Result<bool> {
+    let mut files = vec![];
+    collect(dir, &mut files)?;
+    files.sort();
+
+    let (mut passed, mut failed) = (0, 0);
+
+    for file in files {
+        if fs::read_to_string(&file)?.contains("// nontest") {
+            continue;
+        }
+
+        let failures = run_file(&file, dialect)?;
+
+        if failures.is_empty() {
+            passed += 1;
+            continue;
+        }
+
+        failed += 1;
+        println!("FAIL {}", file.display());
+
+        for failure in failures {
+            println!("    {failure}");
+        }
+    }
+
+    println!("{passed} passed, {failed} failed.");
+
+    Ok(failed == 0)
+}
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "lox") {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::conformance::Expectations;
+
+    #[test]
+    fn check_expectations() {
+        let src = "
+            print 1; // expect: 1
+            print \"\"; // expect:
+            var a = 1 +; // Error at ';': Expect expression.
+            // [line 9] Error at end: Expect '}' after block.
+            // [java line 10] Error: Unexpected character.
+            // [c line 11] Error: Unexpected character.
+            -nil; // expect runtime error: Operand must be a number.
+            print \"a // b\"; // expect: a // b
+        ";
+
+        assert_eq!(
+            Expectations::parse(src),
+            Expectations {
+                output: vec!["1".into(), "".into(), "a // b".into()],
+                errors: vec![
+                    "[line 4] Error at ';': Expect expression.".into(),
+                    "[line 9] Error at end: Expect '}' after block.".into(),
+                    "[line 10] Error: Unexpected character.".into(),
+                ],
+                runtime_error: Some((8, "Operand must be a number.".into())),
+            }
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! Compiler-style error rendering: a header with the message, the file position of the error, and
+//! the offending source lines with every labelled span underlined underneath them.
+//!
+//! ```text
+//! error: Already a variable with this name in this scope.
+//!  --> main.lox:2:9
+//!   |
+//! 1 |     var a = 1;
+//!   |         - first declared here
+//! 2 |     var a = 2;
+//!   |         ^
+//! ```
+
+use std::{
+    fmt::Write,
+    io::{self, IsTerminal},
+    rc::Rc,
+};
+
+use crate::token::{Span, Token};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// A span of source to underline, with an optional message printed next to the underline.
+#[derive(Debug, Clone)]
+pub struct Label {
+    source: Rc<str>,
+    span: Span,
+    msg: String,
+    primary: bool,
+}
+
+impl Label {
+    /// Where the error happened, underlined with `^`.
+    pub fn primary(source: &Rc<str>, span: Span, msg: impl Into<String>) -> Self {
+        Self {
+            source: source.clone(),
+            span,
+            msg: msg.into(),
+            primary: true,
+        }
+    }
+
+    /// Related code that helps explain the error, underlined with `-`.
+    pub fn secondary(source: &Rc<str>, span: Span, msg: impl Into<String>) -> Self {
+        Self {
+            primary: false,
+            ..Self::primary(source, span, msg)
+        }
+    }
+
+    /// Line and column, both 1-based, that the span starts at.
+    fn position(&self) -> (usize, usize) {
+        let before = &self.source[..self.span.start];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        (line, before[line_start..].chars().count() + 1)
+    }
+
+    /// Byte range of the whole source line the span starts on, without its line break.
+    fn line_range(&self) -> (usize, usize) {
+        let start = self.source[..self.span.start]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let end = self.source[self.span.start..]
+            .find('\n')
+            .map_or(self.source.len(), |i| self.span.start + i);
+
+        (start, end)
+    }
+
+    /// Synthetic tokens have no source to point into.
+    fn is_printable(&self) -> bool {
+        !self.source.is_empty() && self.span.start <= self.source.len()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    msg: String,
+    labels: Vec<Label>,
+    notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(msg: impl Into<String>) -> Self {
+        Self {
+            msg: msg.into(),
+            labels: vec![],
+            notes: vec![],
+        }
+    }
+
+    /// Shorthand for a primary label underlining `token`.
+    pub fn at(self, token: &Token) -> Self {
+        self.with_label(Label::primary(&token.source, token.span, ""))
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_help(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Prints the diagnostic to stdout, coloured only when stdout is a terminal.
+    pub fn emit(&self, file: &str) {
+        print!("{}", self.render(file, io::stdout().is_terminal()));
+    }
+
+    pub fn render(&self, file: &str, color: bool) -> String {
+        let paint = |style: &str, text: &str| {
+            if color {
+                format!("{style}{text}{RESET}")
+            } else {
+                text.to_string()
+            }
+        };
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{}{}",
+            paint(RED, "error"),
+            paint(BOLD, &format!(": {}", self.msg))
+        );
+
+        let mut labels = self
+            .labels
+            .iter()
+            .filter(|l| l.is_printable())
+            .collect::<Vec<_>>();
+
+        // The header points at the primary label, whichever order the labels were added in.
+        labels.sort_by_key(|l| !l.primary);
+
+        if let Some(first) = labels.first() {
+            let (line, col) = first.position();
+            let _ = writeln!(out, " {} {file}:{line}:{col}", paint(BLUE, "-->"));
+        }
+
+        labels.sort_by_key(|l| (l.position().0, !l.primary));
+
+        let width = labels
+            .iter()
+            .map(|l| l.position().0.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let gutter = paint(BLUE, &format!("{:width$} |", ""));
+
+        if !labels.is_empty() {
+            let _ = writeln!(out, "{gutter}");
+        }
+
+        let mut printed: Option<(usize, usize)> = None;
+
+        for label in labels {
+            let (line, _) = label.position();
+            let (start, end) = label.line_range();
+
+            // Several labels on one line share a single copy of it.
+            if printed != Some((start, end)) {
+                let number = paint(BLUE, &format!("{line:>width$} |"));
+                let text = label.source[start..end].trim_end_matches('\r');
+                let _ = writeln!(out, "{number} {text}");
+                printed = Some((start, end));
+            }
+
+            // Keep tabs so the underline lines up with whatever width the terminal gives them.
+            let pad = label.source[start..label.span.start]
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect::<String>();
+            let len = label.source[label.span.start..label.span.end.min(end)]
+                .chars()
+                .count()
+                .max(1);
+
+            let (mark, style) = if label.primary {
+                ("^", RED)
+            } else {
+                ("-", BLUE)
+            };
+            let underline = format!("{} {}", mark.repeat(len), label.msg);
+            let _ = writeln!(out, "{gutter} {pad}{}", paint(style, underline.trim_end()));
+        }
+
+        for note in self.notes.iter() {
+            let _ = writeln!(out, "{:width$} {} {note}", "", paint(CYAN, "= help:"));
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use crate::{
+        diagnostics::{Diagnostic, Label},
+        token::Span,
+    };
+
+    #[test]
+    fn check_render() {
+        let src: Rc<str> = "{\n  var a = 1;\n  var a = 2;\n}".into();
+        let diag = Diagnostic::error("Already a variable with this name in this scope.")
+            .with_label(Label::primary(&src, Span { start: 21, end: 22 }, ""))
+            .with_label(Label::secondary(
+                &src,
+                Span { start: 8, end: 9 },
+                "first declared here",
+            ))
+            .with_help("pick another name");
+
+        assert_eq!(
+            diag.render("main.lox", false),
+            [
+                "error: Already a variable with this name in this scope.",
+                " --> main.lox:3:7",
+                "  |",
+                "2 |   var a = 1;",
+                "  |       - first declared here",
+                "3 |   var a = 2;",
+                "  |       ^",
+                "  = help: pick another name",
+                "",
+            ]
+            .join("\n")
+        );
+
+        // Tokens made up by the interpreter have no source, so only the header is left.
+        let diag = Diagnostic::error("Stack overflow.").with_label(Label::primary(
+            &"".into(),
+            Span::default(),
+            "",
+        ));
+        assert_eq!(diag.render("main.lox", false), "error: Stack overflow.\n");
+    }
+}
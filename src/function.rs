@@ -0,0 +1,134 @@
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
+
+use crate::{
+    callable::Callable,
+    class::LoxInstance,
+    environment::Environment,
+    gen_ast::{Function, Stmt},
+    interpreter::{Interpreter, RuntimeError, Unwind},
+    token::Token,
+    value::Value,
+};
+
+/// Runtime representation of a function declared with `fn`, along with the scope it was
+/// declared in so its body can keep reaching enclosing locals after they go out of scope.
+pub struct LoxFunction {
+    name: Token,
+    params: Vec<Token>,
+    body: Rc<Vec<Stmt>>,
+    closure: Rc<RefCell<Environment>>,
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(decl: &Function, closure: Rc<RefCell<Environment>>, is_initializer: bool) -> Self {
+        Self {
+            name: decl.name.clone(),
+            params: decl.params.clone(),
+            body: Rc::clone(&decl.body),
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// Returns a copy of this method whose closure has `this` bound to `instance`.
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> Self {
+        let mut environment = Environment::new(Rc::clone(&self.closure));
+        environment.define("this", Value::Instance(instance));
+
+        Self {
+            name: self.name.clone(),
+            params: self.params.clone(),
+            body: Rc::clone(&self.body),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.lexeme()
+    }
+}
+
+impl Debug for LoxFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<fn {}>", self.name())
+    }
+}
+
+impl Callable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.params.len()
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        _: &Token,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let mut environment = Environment::new(Rc::clone(&self.closure));
+
+        for (param, arg) in self.params.iter().zip(args) {
+            environment.define(param.lexeme(), arg);
+        }
+
+        match interpreter.execute_block(&self.body, Rc::new(RefCell::new(environment))) {
+            // An initializer always hands back the instance, even on a bare `return;`.
+            Ok(()) | Err(Unwind::Return(_)) if self.is_initializer => Ok(self
+                .closure
+                .borrow()
+                .get_local("this")
+                .unwrap_or(Value::Nil)),
+            Ok(()) => Ok(Value::Nil),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(err)) => Err(err),
+        }
+    }
+}
+
+/// The Rust side of a native function. It gets the arguments, already checked against the
+/// declared arity, and reports failure with a message that becomes a runtime error at the call.
+pub type NativeFn = dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Value, String>;
+
+/// A function implemented in Rust and exposed to scripts as a global.
+pub struct NativeFunction {
+    name: String,
+    arity: usize,
+    fun: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new(
+        name: impl Into<String>,
+        arity: usize,
+        fun: impl Fn(&mut Interpreter, Vec<Value>) -> Result<Value, String> + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            arity,
+            fun: Box::new(fun),
+        }
+    }
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl Callable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        (self.fun)(interpreter, args).map_err(|msg| RuntimeError::new(paren.clone(), msg))
+    }
+}
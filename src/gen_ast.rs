@@ -1,34 +1,36 @@
+use std::{cell::Cell, rc::Rc};
+
 use crate::{token::Token, token_type::TokenType};
 
 macro_rules! gen_ast {
-    ($($name:ident, $ty: ident { $($f: ident : $ff: ty),* },)*) => {
+    ($node: ident, $visitor: ident, $($name:ident, $ty: ident { $($(#[$fa: meta])* $f: ident : $ff: ty),* },)*) => {
         #[derive(Debug)]
-        pub enum Expr {
+        pub enum $node {
             $($ty($ty),)*
         }
 
-        impl Expr {
-            pub fn accept<T>(self, visitor: impl Visitor<T>) -> Result<T, ()> {
+        impl $node {
+            pub fn accept<T, E>(&self, visitor: &mut impl $visitor<T, E>) -> Result<T, E> {
                 match self {
                     $(Self::$ty(x)  => x.accept(visitor),)*
                 }
             }
         }
 
-        pub trait Visitor<T> {
+        pub trait $visitor<T, E> {
             $(
-                fn $name(&self, expr: $ty) -> Result<T, ()>;
+                fn $name(&mut self, node: &$ty) -> Result<T, E>;
             )*
         }
 
         $(
             #[derive(Debug)]
             pub struct $ty {
-                $(pub $f: $ff),*
+                $($(#[$fa])* pub $f: $ff),*
             }
 
             impl $ty {
-                pub fn accept<T>(self, visitor: impl Visitor<T>) -> Result<T, ()> {
+                pub fn accept<T, E>(&self, visitor: &mut impl $visitor<T, E>) -> Result<T, E> {
                     visitor.$name(self)
                 }
             }
@@ -36,9 +38,54 @@ macro_rules! gen_ast {
     };
 }
 
+// `depth` is filled in by the resolver: how many scopes out the variable lives, or `None` when it
+// is a global.
 gen_ast!(
+    Expr, ExprVisitor,
+    visit_assign, Assign { name: Token, value: Box<Expr>, depth: Cell<Option<usize>> },
     visit_binary, Binary { left: Box<Expr>, op: Token, right: Box<Expr> },
+    visit_call, Call { callee: Box<Expr>, paren: Token, args: Vec<Expr> },
+    visit_get, Get { object: Box<Expr>, name: Token },
     visit_grouping, Grouping { expr: Box<Expr> },
     visit_literal, Literal { value: TokenType },
+    visit_logical, Logical { left: Box<Expr>, op: Token, right: Box<Expr> },
+    visit_set, Set { object: Box<Expr>, name: Token, value: Box<Expr> },
+    visit_super, Super { keyword: Token, method: Token, depth: Cell<Option<usize>> },
+    visit_this, This { keyword: Token, depth: Cell<Option<usize>> },
     visit_unary, Unary { op: Token, right: Box<Expr> },
+    visit_variable, Variable { name: Token, depth: Cell<Option<usize>> },
+);
+
+impl Expr {
+    /// The token errors about this expression point at. Groupings and literals have none.
+    pub fn token(&self) -> Option<&Token> {
+        match self {
+            Self::Assign(x) => Some(&x.name),
+            Self::Binary(x) => Some(&x.op),
+            Self::Call(x) => Some(&x.paren),
+            Self::Get(x) => Some(&x.name),
+            Self::Grouping(_) | Self::Literal(_) => None,
+            Self::Logical(x) => Some(&x.op),
+            Self::Set(x) => Some(&x.name),
+            Self::Super(x) => Some(&x.keyword),
+            Self::This(x) => Some(&x.keyword),
+            Self::Unary(x) => Some(&x.op),
+            Self::Variable(x) => Some(&x.name),
+        }
+    }
+}
+
+// `doc` holds the `///` comments written above a declaration. Nothing reads it yet; it is kept for
+// tooling such as a doc generator.
+gen_ast!(
+    Stmt, StmtVisitor,
+    visit_block, Block { stmts: Vec<Stmt> },
+    visit_class, Class { name: Token, superclass: Option<Variable>, methods: Vec<Function>, #[allow(unused)] doc: Option<String> },
+    visit_expression, Expression { expr: Box<Expr> },
+    visit_function, Function { name: Token, params: Vec<Token>, body: Rc<Vec<Stmt>>, #[allow(unused)] doc: Option<String> },
+    visit_if, If { cond: Box<Expr>, then_branch: Box<Stmt>, else_branch: Option<Box<Stmt>> },
+    visit_print, Print { expr: Box<Expr> },
+    visit_return, Return { keyword: Token, value: Option<Box<Expr>> },
+    visit_var, Var { name: Token, initializer: Option<Box<Expr>>, #[allow(unused)] doc: Option<String> },
+    visit_while, While { cond: Box<Expr>, body: Box<Stmt> },
 );
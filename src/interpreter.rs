@@ -1,93 +1,791 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::Write,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use crate::{
-    gen_ast::{Expr, Visitor},
+    class::{LoxClass, LoxInstance},
+    environment::Environment,
+    function::{LoxFunction, NativeFunction},
+    gen_ast::{
+        Assign, Binary, Block, Call, Class, Expr, ExprVisitor, Expression, Function, Get, Grouping,
+        If, Literal, Logical, Print, Return, Set, Stmt, StmtVisitor, Super, This, Unary, Var,
+        Variable, While,
+    },
+    stack::{StackGuard, DEFAULT_STACK_LIMIT},
+    token::Token,
     token_type::TokenType,
+    value::Value,
 };
 
-#[derive(Debug, Clone, Copy)]
-pub struct Interpreter;
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub token: Token,
+    pub msg: String,
+}
 
-impl Visitor<TokenType> for Interpreter {
-    fn visit_literal(&self, expr: crate::gen_ast::Literal) -> Result<TokenType, ()> {
-        Ok(expr.value)
+impl RuntimeError {
+    pub fn new(token: Token, msg: impl Into<String>) -> Self {
+        Self {
+            token,
+            msg: msg.into(),
+        }
     }
+}
 
-    fn visit_grouping(&self, expr: crate::gen_ast::Grouping) -> Result<TokenType, ()> {
-        self.evaluate(*expr.expr)
+/// Why statement execution stopped early: either a `return` unwinding to the enclosing call, or
+/// a runtime error unwinding all the way out.
+#[derive(Debug)]
+pub enum Unwind {
+    Return(Value),
+    Error(RuntimeError),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(err: RuntimeError) -> Self {
+        Self::Error(err)
     }
+}
 
-    fn visit_unary(&self, expr: crate::gen_ast::Unary) -> Result<TokenType, ()> {
-        let right = self.evaluate(*expr.right)?;
+/// Where `print` statements write to.
+#[derive(Debug, Clone, Default)]
+pub enum Output {
+    #[default]
+    Stdout,
+    /// Collects everything printed, one `\n`-terminated line per statement.
+    Buffer(Rc<RefCell<String>>),
+}
 
-        match (expr.op.typ, &right) {
-            (TokenType::Minus, TokenType::Number(n)) => {
-                dbg!(-n);
+impl Output {
+    fn print(&self, value: &Value) {
+        match self {
+            Self::Stdout => println!("{value}"),
+            Self::Buffer(buf) => {
+                let _ = writeln!(buf.borrow_mut(), "{value}");
             }
-            (TokenType::Bang, _) => {
-                dbg!(!self.is_truthy(&right));
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    stack: StackGuard,
+    output: Output,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new(Output::Stdout)
+    }
+}
+
+impl ExprVisitor<Value, RuntimeError> for Interpreter {
+    fn visit_assign(&mut self, expr: &Assign) -> Result<Value, RuntimeError> {
+        let value = self.evaluate(&expr.value)?;
+
+        match expr.depth.get() {
+            Some(distance) => {
+                self.environment
+                    .borrow_mut()
+                    .assign_at(distance, &expr.name, value.clone())?
             }
+            None => self
+                .globals
+                .borrow_mut()
+                .assign(&expr.name, value.clone())?,
+        };
+
+        Ok(value)
+    }
+
+    fn visit_call(&mut self, expr: &Call) -> Result<Value, RuntimeError> {
+        let callee = self.evaluate(&expr.callee)?;
+
+        let mut args = Vec::with_capacity(expr.args.len());
+        for arg in expr.args.iter() {
+            args.push(self.evaluate(arg)?);
+        }
+
+        self.call(&callee, &expr.paren, args)
+    }
+
+    fn visit_get(&mut self, expr: &Get) -> Result<Value, RuntimeError> {
+        match self.evaluate(&expr.object)? {
+            Value::Instance(instance) => LoxInstance::get(&instance, &expr.name),
+            _ => Err(RuntimeError::new(
+                expr.name.clone(),
+                "Only instances have properties.",
+            )),
+        }
+    }
+
+    fn visit_literal(&mut self, expr: &Literal) -> Result<Value, RuntimeError> {
+        Ok(expr.value.clone().into())
+    }
+
+    fn visit_grouping(&mut self, expr: &Grouping) -> Result<Value, RuntimeError> {
+        self.evaluate(&expr.expr)
+    }
+
+    /// Short-circuits and yields whichever operand decided the result, not a coerced bool.
+    fn visit_logical(&mut self, expr: &Logical) -> Result<Value, RuntimeError> {
+        let left = self.evaluate(&expr.left)?;
+
+        let decided = match expr.op.typ {
+            TokenType::Or => self.is_truthy(&left),
+            _ => !self.is_truthy(&left),
+        };
+
+        if decided {
+            return Ok(left);
+        }
+
+        self.evaluate(&expr.right)
+    }
+
+    fn visit_set(&mut self, expr: &Set) -> Result<Value, RuntimeError> {
+        let Value::Instance(instance) = self.evaluate(&expr.object)? else {
+            return Err(RuntimeError::new(
+                expr.name.clone(),
+                "Only instances have fields.",
+            ));
+        };
+
+        let value = self.evaluate(&expr.value)?;
+        instance.borrow_mut().set(&expr.name, value.clone());
+
+        Ok(value)
+    }
+
+    fn visit_super(&mut self, expr: &Super) -> Result<Value, RuntimeError> {
+        let distance = expr.depth.get().unwrap_or_default();
+        let environment = self.environment.borrow();
+
+        let superclass = environment.get_local_at(distance, "super");
+        // `this` always lives in the scope right inside the one holding `super`.
+        let object = environment.get_local_at(distance.saturating_sub(1), "this");
+
+        let (Some(Value::Class(superclass)), Some(Value::Instance(object))) = (superclass, object)
+        else {
+            return Err(RuntimeError::new(
+                expr.keyword.clone(),
+                "Can't use 'super' outside of a subclass method.",
+            ));
+        };
+
+        match superclass.find_method(expr.method.lexeme()) {
+            Some(method) => Ok(Value::Function(Rc::new(method.bind(object)))),
+            None => Err(RuntimeError::new(
+                expr.method.clone(),
+                format!("Undefined property '{}'.", expr.method.lexeme()),
+            )),
+        }
+    }
+
+    fn visit_this(&mut self, expr: &This) -> Result<Value, RuntimeError> {
+        self.look_up_variable(&expr.keyword, expr.depth.get())
+    }
+
+    fn visit_unary(&mut self, expr: &Unary) -> Result<Value, RuntimeError> {
+        let right = self.evaluate(&expr.right)?;
+
+        match (&expr.op.typ, &right) {
+            (TokenType::Minus, Value::Number(n)) => Ok(Value::Number(-n)),
+            (TokenType::Bang, _) => Ok(Value::Bool(!self.is_truthy(&right))),
+            _ => Err(RuntimeError::new(
+                expr.op.clone(),
+                "Operand must be a number.",
+            )),
+        }
+    }
+
+    fn visit_binary(&mut self, expr: &Binary) -> Result<Value, RuntimeError> {
+        let left = self.evaluate(&expr.left)?;
+        let right = self.evaluate(&expr.right)?;
+
+        let value = match (&expr.op.typ, left, right) {
+            // Any two values can be compared for equality; different types are never equal.
+            (TokenType::EqualEqual, l, r) => Value::Bool(l == r),
+            (TokenType::BangEqual, l, r) => Value::Bool(l != r),
+            (TokenType::Plus, Value::Number(l), Value::Number(r)) => Value::Number(l + r),
+            (TokenType::Plus, Value::String(l), Value::String(r)) => Value::String(l + &r),
+            (TokenType::Plus, _, _) => {
+                return Err(RuntimeError::new(
+                    expr.op.clone(),
+                    "Operands must be two numbers or two strings.",
+                ))
+            }
+            (op, Value::Number(l), Value::Number(r)) => match op {
+                TokenType::Minus => Value::Number(l - r),
+                TokenType::Slash => Value::Number(l / r),
+                TokenType::Star => Value::Number(l * r),
+                TokenType::Greater => Value::Bool(l > r),
+                TokenType::GreaterEqual => Value::Bool(l >= r),
+                TokenType::Less => Value::Bool(l < r),
+                TokenType::LessEqual => Value::Bool(l <= r),
+                _ => unreachable!(),
+            },
             _ => {
-                dbg!(0.0);
+                return Err(RuntimeError::new(
+                    expr.op.clone(),
+                    "Operands must be numbers.",
+                ))
             }
         };
 
-        unreachable!()
+        Ok(value)
     }
 
-    fn visit_binary(&self, expr: crate::gen_ast::Binary) -> Result<TokenType, ()> {
-        let left = self.evaluate(*expr.left)?;
-        let right = self.evaluate(*expr.right)?;
+    fn visit_variable(&mut self, expr: &Variable) -> Result<Value, RuntimeError> {
+        self.look_up_variable(&expr.name, expr.depth.get())
+    }
+}
 
-        match (left, right) {
-            (TokenType::Number(l), TokenType::Number(r)) => match expr.op.typ {
-                TokenType::Minus => {
-                    dbg!(l - r);
-                }
-                TokenType::Slash => {
-                    dbg!(l / r);
-                }
-                TokenType::Star => {
-                    dbg!(l * r);
-                }
-                TokenType::Plus => {
-                    dbg!(l + r);
-                }
-                TokenType::Greater => {
-                    dbg!(l > r);
-                }
-                TokenType::GreaterEqual => {
-                    dbg!(l >= r);
-                }
-                TokenType::Less => {
-                    dbg!(l < r);
-                }
-                TokenType::LessEqual => {
-                    dbg!(l >= r);
-                }
-                TokenType::BangEqual => {
-                    dbg!(l != r);
-                }
-                TokenType::EqualEqual => {
-                    dbg!(l == r);
+impl StmtVisitor<(), Unwind> for Interpreter {
+    fn visit_block(&mut self, stmt: &Block) -> Result<(), Unwind> {
+        let environment = Environment::new(Rc::clone(&self.environment));
+        self.execute_block(&stmt.stmts, Rc::new(RefCell::new(environment)))
+    }
+
+    fn visit_class(&mut self, stmt: &Class) -> Result<(), Unwind> {
+        let superclass = match &stmt.superclass {
+            Some(superclass) => match self.visit_variable(superclass)? {
+                Value::Class(class) => Some(class),
+                _ => {
+                    return Err(RuntimeError::new(
+                        superclass.name.clone(),
+                        "Superclass must be a class.",
+                    )
+                    .into())
                 }
-                _ => unreachable!(),
             },
-            _ => panic!("Both operands should be numbers"),
+            None => None,
         };
 
-        Err(())
+        let enclosing = Rc::clone(&self.environment);
+
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::new(Rc::clone(&self.environment));
+            environment.define("super", Value::Class(Rc::clone(superclass)));
+            self.environment = Rc::new(RefCell::new(environment));
+        }
+
+        let methods = stmt
+            .methods
+            .iter()
+            .map(|method| {
+                let function = LoxFunction::new(
+                    method,
+                    Rc::clone(&self.environment),
+                    method.name.lexeme() == "init",
+                );
+
+                (method.name.lexeme().to_string(), Rc::new(function))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let class = LoxClass::new(stmt.name.lexeme(), superclass, methods);
+
+        self.environment = enclosing;
+        self.environment
+            .borrow_mut()
+            .define(stmt.name.lexeme(), Value::Class(Rc::new(class)));
+
+        Ok(())
+    }
+
+    fn visit_expression(&mut self, stmt: &Expression) -> Result<(), Unwind> {
+        self.evaluate(&stmt.expr)?;
+        Ok(())
+    }
+
+    fn visit_function(&mut self, stmt: &Function) -> Result<(), Unwind> {
+        let function = LoxFunction::new(stmt, Rc::clone(&self.environment), false);
+
+        self.environment
+            .borrow_mut()
+            .define(stmt.name.lexeme(), Value::Function(Rc::new(function)));
+
+        Ok(())
+    }
+
+    fn visit_if(&mut self, stmt: &If) -> Result<(), Unwind> {
+        let cond = self.evaluate(&stmt.cond)?;
+
+        if self.is_truthy(&cond) {
+            self.execute(&stmt.then_branch)
+        } else if let Some(else_branch) = &stmt.else_branch {
+            self.execute(else_branch)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn visit_print(&mut self, stmt: &Print) -> Result<(), Unwind> {
+        let value = self.evaluate(&stmt.expr)?;
+        self.output.print(&value);
+        Ok(())
+    }
+
+    fn visit_return(&mut self, stmt: &Return) -> Result<(), Unwind> {
+        let value = match &stmt.value {
+            Some(x) => self.evaluate(x)?,
+            None => Value::Nil,
+        };
+
+        Err(Unwind::Return(value))
+    }
+
+    fn visit_var(&mut self, stmt: &Var) -> Result<(), Unwind> {
+        let value = match &stmt.initializer {
+            Some(x) => self.evaluate(x)?,
+            None => Value::Nil,
+        };
+
+        self.environment
+            .borrow_mut()
+            .define(stmt.name.lexeme(), value);
+
+        Ok(())
+    }
+
+    fn visit_while(&mut self, stmt: &While) -> Result<(), Unwind> {
+        loop {
+            let cond = self.evaluate(&stmt.cond)?;
+
+            if !self.is_truthy(&cond) {
+                break;
+            }
+
+            self.execute(&stmt.body)?;
+        }
+
+        Ok(())
     }
 }
 
 impl Interpreter {
-    fn evaluate(&self, expr: Expr) -> Result<TokenType, ()> {
-        expr.accept(*self)
+    pub fn new(output: Output) -> Self {
+        let globals = Rc::new(RefCell::new(Environment::default()));
+
+        let mut interpreter = Self {
+            environment: Rc::clone(&globals),
+            globals,
+            stack: StackGuard::new(DEFAULT_STACK_LIMIT),
+            output,
+        };
+
+        interpreter.define_native("clock", 0, |_, _| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_err(|err| err.to_string())?;
+
+            Ok(Value::Number(now.as_secs_f64()))
+        });
+
+        interpreter
+    }
+
+    /// Makes a Rust closure callable from scripts as the global function `name`. Calls with the
+    /// wrong number of arguments are rejected before `fun` runs; an `Err` it returns becomes a
+    /// runtime error pointing at the call.
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        fun: impl Fn(&mut Interpreter, Vec<Value>) -> Result<Value, String> + 'static,
+    ) {
+        let native = NativeFunction::new(name, arity, fun);
+
+        self.globals
+            .borrow_mut()
+            .define(name, Value::NativeFunction(Rc::new(native)));
+    }
+
+    /// How many bytes of stack scripts may use before failing with a stack overflow. Raise it only
+    /// when running on a thread with a correspondingly larger stack.
+    pub fn with_stack_limit(mut self, bytes: usize) -> Self {
+        self.stack = StackGuard::new(bytes);
+        self
+    }
+
+    pub fn stack_limit(&self) -> usize {
+        self.stack.limit()
+    }
+
+    pub fn set_output(&mut self, output: Output) {
+        self.output = output;
+    }
+
+    /// Reads a global, including the natives defined by the host.
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.globals.borrow().get_local(name)
+    }
+
+    /// Like `get_global`, but fails the way reading an undefined variable does.
+    pub(crate) fn look_up_global(&self, name: &Token) -> Result<Value, RuntimeError> {
+        self.globals.borrow().get(name)
+    }
+
+    /// Defines or overwrites a global, the same as a top-level `var` would.
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.globals.borrow_mut().define(name, value);
+    }
+
+    /// Calls `callee` like a call expression would, reporting errors at `paren`.
+    pub fn call(
+        &mut self,
+        callee: &Value,
+        paren: &Token,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let Some(callable) = callee.as_callable() else {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                "Can only call functions and classes.",
+            ));
+        };
+
+        if args.len() != callable.arity() {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                format!(
+                    "Expected {} arguments but got {}.",
+                    callable.arity(),
+                    args.len()
+                ),
+            ));
+        }
+
+        self.guarded(|this| callable.call(this, paren, args))
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        // Groupings and literals have no token to blame, but the parser already limits how deeply
+        // groupings nest, and whatever they wrap is checked in turn.
+        if self.stack.exhausted() {
+            if let Some(token) = expr.token() {
+                return Err(RuntimeError::new(token.clone(), "Stack overflow."));
+            }
+        }
+
+        expr.accept(self)
+    }
+
+    /// Runs `f` with the stack guard measuring from here, unless something further out already is.
+    fn guarded<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let outermost = self.stack.start();
+        let result = f(self);
+
+        if outermost {
+            self.stack.stop();
+        }
+
+        result
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        stmt.accept(self)
+    }
+
+    /// Runs `stmts` inside `environment`, restoring the current scope afterwards even if a
+    /// statement fails or returns.
+    pub(crate) fn execute_block(
+        &mut self,
+        stmts: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), Unwind> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+
+        let result = stmts.iter().try_for_each(|stmt| self.execute(stmt));
+
+        self.environment = previous;
+
+        result
+    }
+
+    fn look_up_variable(&self, name: &Token, depth: Option<usize>) -> Result<Value, RuntimeError> {
+        match depth {
+            Some(distance) => self.environment.borrow().get_at(distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    fn is_truthy(&self, value: &Value) -> bool {
+        !matches!(value, Value::Bool(false) | Value::Nil)
+    }
+
+    /// Executes every statement in order. If the program ends with an expression statement, its
+    /// value is handed back so the REPL can echo it.
+    pub(crate) fn interpret(&mut self, stmts: &[Stmt]) -> Result<Option<Value>, RuntimeError> {
+        self.guarded(|this| {
+            let mut value = None;
+
+            for stmt in stmts {
+                value = match stmt {
+                    Stmt::Expression(x) => Some(this.evaluate(&x.expr)?),
+                    _ => match this.execute(stmt) {
+                        Ok(()) => None,
+                        // The resolver rejects top-level `return`, but stop cleanly regardless.
+                        Err(Unwind::Return(_)) => break,
+                        Err(Unwind::Error(err)) => return Err(err),
+                    },
+                };
+            }
+
+            Ok(value)
+        })
     }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        interpreter::Interpreter, lexer::Lexer, parser::Parser, resolver::Resolver, value::Value,
+    };
 
-    fn is_truthy(&self, tty: &TokenType) -> bool {
-        !matches!(tty, TokenType::False | TokenType::Nil)
+    fn eval(src: &str) -> Result<Value, String> {
+        eval_with(&mut Interpreter::default(), src)
     }
 
-    pub fn interpret(expr: Expr) {
-        Self::evaluate(&Self {}, expr).ok();
+    fn eval_with(interpreter: &mut Interpreter, src: &str) -> Result<Value, String> {
+        let stmts = Parser::new(Lexer::new(src).flatten()).parse().unwrap();
+        Resolver::default().resolve_program(&stmts).unwrap();
+
+        interpreter
+            .interpret(&stmts)
+            .map(Option::unwrap)
+            .map_err(|e| e.msg)
+    }
+
+    /// Runs each source after `defs`, expecting the value of its last expression or its runtime
+    /// error.
+    fn check_table(defs: &str, table: &[(&str, Result<Value, &str>)]) {
+        for (src, expected) in table {
+            let expected = expected.clone().map_err(String::from);
+            assert_eq!(eval(&format!("{defs}{src}")), expected, "{src}");
+        }
+    }
+
+    #[test]
+    fn check_operators() {
+        use Value::{Bool, Nil, Number};
+
+        let str = |s: &str| Value::String(s.into());
+        let numbers = "Operands must be numbers.";
+        let plus = "Operands must be two numbers or two strings.";
+
+        let table: &[(&str, Result<Value, &str>)] = &[
+            ("1 + 2;", Ok(Number(3.0))),
+            ("\"a\" + \"b\";", Ok(str("ab"))),
+            ("\"a\" + 1;", Err(plus)),
+            ("1 + \"a\";", Err(plus)),
+            ("nil + nil;", Err(plus)),
+            ("true + true;", Err(plus)),
+            ("5 - 3;", Ok(Number(2.0))),
+            ("\"a\" - 1;", Err(numbers)),
+            ("nil - 1;", Err(numbers)),
+            ("2 * 3;", Ok(Number(6.0))),
+            ("\"a\" * 3;", Err(numbers)),
+            ("1 / 4;", Ok(Number(0.25))),
+            ("true / 1;", Err(numbers)),
+            ("1 < 2;", Ok(Bool(true))),
+            ("2 < 2;", Ok(Bool(false))),
+            ("2 <= 2;", Ok(Bool(true))),
+            ("3 <= 2;", Ok(Bool(false))),
+            ("1 <= 2;", Ok(Bool(true))),
+            ("2 > 1;", Ok(Bool(true))),
+            ("2 > 2;", Ok(Bool(false))),
+            ("2 >= 2;", Ok(Bool(true))),
+            ("1 >= 2;", Ok(Bool(false))),
+            ("\"a\" < \"b\";", Err(numbers)),
+            ("nil <= 1;", Err(numbers)),
+            ("true > false;", Err(numbers)),
+            ("1 == 1;", Ok(Bool(true))),
+            ("1 == 2;", Ok(Bool(false))),
+            ("\"a\" == \"a\";", Ok(Bool(true))),
+            ("\"a\" == \"b\";", Ok(Bool(false))),
+            ("nil == nil;", Ok(Bool(true))),
+            ("true == true;", Ok(Bool(true))),
+            ("true == false;", Ok(Bool(false))),
+            ("1 == \"1\";", Ok(Bool(false))),
+            ("nil == false;", Ok(Bool(false))),
+            ("0 == false;", Ok(Bool(false))),
+            ("0 / 0 == 0 / 0;", Ok(Bool(false))),
+            ("1 != 2;", Ok(Bool(true))),
+            ("\"a\" != \"a\";", Ok(Bool(false))),
+            ("nil != false;", Ok(Bool(true))),
+            ("\"1\" != 1;", Ok(Bool(true))),
+            ("fn f() {} f == f;", Ok(Bool(true))),
+            ("class A {} A() == A();", Ok(Bool(false))),
+            ("-\"a\";", Err("Operand must be a number.")),
+            ("!nil;", Ok(Bool(true))),
+            ("!0;", Ok(Bool(false))),
+            ("nil;", Ok(Nil)),
+        ];
+
+        check_table("", table);
+    }
+
+    #[test]
+    fn check_functions() {
+        let defs = "
+            fn add(a, b) { return a + b; }
+            fn nothing() { return; }
+            fn twice(f, x) { return f(f(x)); }
+            fn inc(x) { return x + 1; }
+        ";
+
+        let table: &[(&str, Result<Value, &str>)] = &[
+            ("add(1, 2);", Ok(Value::Number(3.0))),
+            ("nothing();", Ok(Value::Nil)),
+            ("twice(inc, 1);", Ok(Value::Number(3.0))),
+            ("add(1);", Err("Expected 2 arguments but got 1.")),
+            ("\"add\"();", Err("Can only call functions and classes.")),
+        ];
+
+        check_table(defs, table);
+    }
+
+    #[test]
+    fn check_closures() {
+        // Both closures share the one `count`, which outlives the call that declared it.
+        let src = "
+            var inc;
+            var get;
+            fn make() {
+                var count = 0;
+                fn i() { count = count + 1; }
+                fn g() { return count; }
+                inc = i;
+                get = g;
+            }
+
+            make();
+            inc();
+            inc();
+            get();
+        ";
+        assert_eq!(eval(src), Ok(Value::Number(2.0)));
+    }
+
+    #[test]
+    fn check_classes() {
+        let defs = "
+            class Point {
+                init(x, y) {
+                    this.x = x;
+                    this.y = y;
+                    if (x == 0) return;
+                    this.nonzero = true;
+                }
+
+                sum() { return this.x + this.y; }
+            }
+
+            var p = Point(1, 2);
+            var sum = p.sum;
+        ";
+
+        let table: &[(&str, Result<Value, &str>)] = &[
+            ("p.x + p.y;", Ok(Value::Number(3.0))),
+            // A bound method keeps the instance it was taken from.
+            ("sum();", Ok(Value::Number(3.0))),
+            ("p.x = 10; sum();", Ok(Value::Number(12.0))),
+            // Initializers hand back `this`, even through a bare `return;` or when called directly.
+            ("Point(0, 5).y;", Ok(Value::Number(5.0))),
+            ("p.init(0, 0) == p;", Ok(Value::Bool(true))),
+            ("p.nonzero;", Ok(Value::Bool(true))),
+            ("Point(0, 0).nonzero;", Err("Undefined property 'nonzero'.")),
+            ("Point(1);", Err("Expected 2 arguments but got 1.")),
+            ("p.sum.x;", Err("Only instances have properties.")),
+        ];
+
+        check_table(defs, table);
+    }
+
+    #[test]
+    fn check_inheritance() {
+        let defs = "
+            class A {
+                name() { return \"A\"; }
+                greet() { return \"hi from \" + this.name(); }
+            }
+
+            class B < A {}
+
+            class C < B {
+                name() { return \"C\"; }
+                // `super` skips B, which doesn't define `greet`, and finds it on A.
+                greet() { return super.greet() + \" via \" + super.name(); }
+            }
+
+            var NotAClass = \"nope\";
+        ";
+
+        let str = |s: &str| Value::String(s.into());
+        let table: &[(&str, Result<Value, &str>)] = &[
+            ("B().greet();", Ok(str("hi from A"))),
+            ("C().greet();", Ok(str("hi from C via A"))),
+            ("class D < NotAClass {}", Err("Superclass must be a class.")),
+        ];
+
+        check_table(defs, table);
+    }
+
+    #[test]
+    fn check_natives() {
+        let mut interpreter = Interpreter::default();
+
+        interpreter.define_native("half", 1, |_, args| match args[0] {
+            Value::Number(n) => Ok(Value::Number(n / 2.0)),
+            _ => Err("Can only halve numbers.".into()),
+        });
+
+        assert_eq!(
+            eval_with(&mut interpreter, "half(3);"),
+            Ok(Value::Number(1.5))
+        );
+        assert_eq!(
+            eval_with(&mut interpreter, "half(\"a\");"),
+            Err("Can only halve numbers.".into())
+        );
+        assert_eq!(
+            eval_with(&mut interpreter, "half();"),
+            Err("Expected 1 arguments but got 0.".into())
+        );
+        assert_eq!(eval("clock() > 0;"), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn check_stack_overflow() {
+        assert_eq!(
+            eval("fn f(n) { return f(n + 1); } f(0);"),
+            Err("Stack overflow.".into())
+        );
+    }
+
+    #[test]
+    fn check_deep_recursion() {
+        const STACK_SIZE: usize = 128 * 1024 * 1024;
+
+        let result = std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(|| {
+                let src = "fn f(n) { if (n == 0) return 0; return f(n - 1) + 1; } f(5000);";
+                let mut interpreter = Interpreter::default().with_stack_limit(STACK_SIZE / 2);
+                // Values hold `Rc`s, so only their text can leave the thread.
+                eval_with(&mut interpreter, src).map(|value| value.to_string())
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(result, Ok("5000".into()));
     }
 }
@@ -1,35 +1,103 @@
-use crate::{lox::Lox, token::Token, token_type::TokenType};
+use std::{collections::VecDeque, fmt::Display, rc::Rc};
+
+use crate::{
+    token::{Span, Token},
+    token_type::{Dialect, TokenType},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LexErrorKind {
+    UnexpectedCharacter,
+    UnterminatedString,
+    UnterminatedComment,
+    InvalidEscape,
+    MalformedNumber,
+}
+
+impl Display for LexErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedCharacter => write!(f, "Unexpected character."),
+            Self::UnterminatedString => write!(f, "Unterminated string."),
+            Self::UnterminatedComment => write!(f, "Unterminated block comment."),
+            Self::InvalidEscape => write!(f, "Invalid escape sequence."),
+            Self::MalformedNumber => write!(f, "Malformed number literal."),
+        }
+    }
+}
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub line: usize,
+    /// 1-based, counted in characters.
+    pub column: usize,
+    pub span: Span,
+    /// The source text the error points at.
+    pub text: String,
+}
+
+/// Walks the source by byte offset. Tokens only point back into the source through their span,
+/// so scanning never copies lexemes.
 #[derive(Debug)]
 pub struct Lexer {
+    /// Byte offsets into `text` of the token being scanned and of the next character.
     start: usize,
     current: usize,
     line: usize,
-    source: Vec<char>,
-    tokens: Vec<Token>,
+    /// Characters consumed so far on the current line.
+    column: usize,
+    /// Where the token being scanned begins, since `line` moves on inside multi-line strings.
+    start_line: usize,
+    start_column: usize,
+    text: Rc<str>,
+    /// What the last `scan` produced and `next` hasn't handed out yet: a token, one or more errors,
+    /// or nothing at all for whitespace and comments.
+    pending: VecDeque<Result<Token, LexError>>,
+    /// Set once `Eof` has been handed out.
+    done: bool,
+    dialect: Dialect,
 }
 
 impl Lexer {
-    pub fn new(source: &str) -> Self {
+    /// Every token keeps a handle on the source so diagnostics can quote it, which means the
+    /// source has to live in an `Rc<str>`. Passing one in shares it; passing a `&str` copies it
+    /// once, here, and never again.
+    pub fn new(source: impl Into<Rc<str>>) -> Self {
         Self {
-            source: source.chars().collect(),
-            tokens: vec![],
+            text: source.into(),
+            pending: VecDeque::new(),
+            done: false,
+            dialect: Dialect::default(),
             start: 0,
             current: 0,
             line: 1,
+            column: 0,
+            start_line: 1,
+            start_column: 1,
         }
     }
 
-    pub fn parse(mut self) -> Result<Vec<Token>, ()> {
-        while !self.at_end() {
-            self.start = self.current;
-            self.scan();
-        }
+    /// Switches which keyword spellings are recognised.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
 
-        self.tokens
-            .push(Token::new(TokenType::Eof, None, self.line));
+    /// Scans the whole source. Lexing carries on past bad input, so the tokens come back together
+    /// with every error that was found along the way.
+    pub fn parse(self) -> (Vec<Token>, Vec<LexError>) {
+        let mut tokens = vec![];
+        let mut errors = vec![];
 
-        Ok(self.tokens)
+        for item in self {
+            match item {
+                Ok(token) => tokens.push(token),
+                Err(err) => errors.push(err),
+            }
+        }
+
+        (tokens, errors)
     }
 
     fn scan(&mut self) {
@@ -80,128 +148,302 @@ impl Lexer {
             }
             '/' => {
                 if self.is('/') {
-                    while self.peek() != '\n' && !self.at_end() {
-                        self.advance();
-                    }
+                    self.line_comment();
+                } else if self.is('*') {
+                    self.block_comment();
                 } else {
                     self.add(TokenType::Slash)
                 }
             }
             '"' => self.string(),
             '0'..='9' => self.number(),
-            '\n' => self.line += 1,
+            '\n' => self.newline(),
             ' ' | '\r' | '\t' => {}
             x => {
-                if x.is_alphabetic() {
+                if x.is_ascii_alphabetic() || x == '_' {
                     self.identifier()
                 } else {
-                    Lox::report(self.line, x.into(), "Unexpected character!".into());
+                    self.error(LexErrorKind::UnexpectedCharacter);
                 }
             }
         }
     }
 
+    fn line_comment(&mut self) {
+        while self.peek() != '\n' && !self.at_end() {
+            self.advance();
+        }
+
+        // Exactly three slashes make a doc comment; `////` and longer are plain comments.
+        let comment = &self.text[self.start..self.current];
+
+        if comment.starts_with("///") && !comment.starts_with("////") {
+            self.add(TokenType::DocComment);
+        }
+    }
+
+    /// Block comments nest, so `/* /* */ */` is a single comment.
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.at_end() {
+                let opening = Span {
+                    start: self.start,
+                    end: self.start + 2,
+                };
+                self.error_at(
+                    LexErrorKind::UnterminatedComment,
+                    opening,
+                    self.start_line,
+                    self.start_column,
+                );
+                return;
+            }
+
+            match self.advance() {
+                '/' if self.is('*') => depth += 1,
+                '*' if self.is('/') => depth -= 1,
+                '\n' => self.newline(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Identifiers are ASCII only: a letter or `_`, followed by any number of letters, digits and
+    /// underscores. Anything else, accented letters included, is an unexpected character.
     fn identifier(&mut self) {
-        while self.peek().is_alphanumeric() {
+        while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
             self.advance();
         }
 
-        let value = self
-            .source
-            .get(self.start..self.current)
-            .unwrap()
-            .iter()
-            .collect::<String>();
-
-        let tok = match value.as_str() {
-            "and" => TokenType::And,
-            "class" => TokenType::Class,
-            "else" => TokenType::Else,
-            "false" => TokenType::False,
-            "for" => TokenType::For,
-            "fn" => TokenType::Fn,
-            "if" => TokenType::If,
-            "nil" => TokenType::Nil,
-            "or" => TokenType::Or,
-            "print" => TokenType::Print,
-            "return" => TokenType::Return,
-            "super" => TokenType::Super,
-            "this" => TokenType::This,
-            "true" => TokenType::True,
-            "var" => TokenType::Var,
-            "while" => TokenType::While,
-            _ => TokenType::Id(value),
-        };
+        let tok = TokenType::keyword(&self.text[self.start..self.current], self.dialect)
+            .unwrap_or(TokenType::Id);
 
         self.add(tok);
     }
 
+    /// Decimal numbers with an optional fraction and exponent, or integers in hex (`0x1F`) and
+    /// binary (`0b1010`). Digits may be grouped with `_`, as in `1_000_000`.
     fn number(&mut self) {
-        loop {
-            match self.peek() {
-                '0'..='9' => {
-                    self.advance();
-                }
-                '.' => {
-                    if let '0'..='9' = self.peek_next() {
-                        self.advance();
-                    } else {
-                        break;
-                    }
-                }
-                _ => break,
+        // Always an ASCII digit, see `scan`.
+        let first = self.text.as_bytes()[self.start] as char;
+
+        let value = match (first, self.peek()) {
+            ('0', 'x' | 'X') => {
+                self.advance();
+                self.integer(16)
             }
+            ('0', 'b' | 'B') => {
+                self.advance();
+                self.integer(2)
+            }
+            _ => self.decimal(first),
+        };
+
+        // Anything word-like straight after the digits (`0b12`, `1abc`) belongs to the same bad
+        // literal rather than starting an identifier.
+        let mut trailing = false;
+
+        while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
+            self.advance();
+            trailing = true;
         }
 
-        let value = self
-            .source
-            .get(self.start..self.current)
-            .unwrap()
-            .iter()
-            .collect::<String>();
+        match value {
+            Some(n) if !trailing => self.add(TokenType::Number(n)),
+            _ => self.error(LexErrorKind::MalformedNumber),
+        }
+    }
+
+    fn integer(&mut self, radix: u32) -> Option<f64> {
+        let digits = self.digits(radix)?;
 
-        self.add(TokenType::Number(value.parse().unwrap()))
+        if digits.is_empty() {
+            return None;
+        }
+
+        Some(digits.chars().fold(0.0, |n, d| {
+            n * radix as f64 + d.to_digit(radix).unwrap() as f64
+        }))
     }
 
-    fn string(&mut self) {
-        if self.at_end() {
-            Lox::error(self.line, "Unterminated string!".into());
-            return;
+    fn decimal(&mut self, first: char) -> Option<f64> {
+        let mut text = String::from(first);
+        text += &self.digits(10)?;
+
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
+            text.push(self.advance());
+            text += &self.digits(10)?;
         }
 
-        while self.peek() != '"' {
-            if self.peek() == '\n' {
-                self.line += 1;
+        if let 'e' | 'E' = self.peek() {
+            text.push(self.advance());
+
+            if let '+' | '-' = self.peek() {
+                text.push(self.advance());
             }
 
-            self.advance();
+            let exponent = self.digits(10)?;
+
+            if exponent.is_empty() {
+                return None;
+            }
+
+            text += &exponent;
         }
 
-        // Closing "
-        self.advance();
+        text.parse().ok()
+    }
 
-        let value = self
-            .source
-            .get(self.start + 1..self.current - 1)
-            .unwrap()
-            .iter()
-            .collect::<String>();
+    /// Consumes digits in `radix` along with any `_` separators, returning the digits with the
+    /// separators stripped. A run that ends in a separator is malformed.
+    fn digits(&mut self, radix: u32) -> Option<String> {
+        let mut digits = String::new();
+        let mut last = '\0';
+
+        while self.peek().is_digit(radix) || self.peek() == '_' {
+            last = self.advance();
+
+            if last != '_' {
+                digits.push(last);
+            }
+        }
+
+        (last != '_').then_some(digits)
+    }
+
+    fn string(&mut self) {
+        let mut value = String::new();
+
+        loop {
+            if self.at_end() {
+                // Point at the opening quote, which is where the mistake most likely is.
+                let quote = Span {
+                    start: self.start,
+                    end: self.start + 1,
+                };
+                self.error_at(
+                    LexErrorKind::UnterminatedString,
+                    quote,
+                    self.start_line,
+                    self.start_column,
+                );
+                return;
+            }
+
+            match self.advance() {
+                '"' => break,
+                '\\' => {
+                    let start = self.current - 1;
+                    let column = self.column;
+
+                    match self.escape() {
+                        Some(c) => value.push(c),
+                        None => {
+                            let span = Span {
+                                start,
+                                end: self.current,
+                            };
+                            self.error_at(LexErrorKind::InvalidEscape, span, self.line, column);
+                        }
+                    }
+                }
+                '\n' => {
+                    self.newline();
+                    value.push('\n');
+                }
+                c => value.push(c),
+            }
+        }
 
         self.add(TokenType::String(value));
     }
 
+    /// Decodes the escape sequence after a `\`. Returns `None` for an unknown or malformed one,
+    /// having consumed as much of it as belongs to the string.
+    fn escape(&mut self) -> Option<char> {
+        let c = match self.peek() {
+            // Leave these for `string` to deal with.
+            '\0' | '\n' => return None,
+            c => c,
+        };
+
+        self.advance();
+
+        match c {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            '"' => Some('"'),
+            '\\' => Some('\\'),
+            'u' => {
+                if !self.is('{') {
+                    return None;
+                }
+
+                let mut code = String::new();
+
+                while self.peek().is_ascii_hexdigit() {
+                    code.push(self.advance());
+                }
+
+                if !self.is('}') {
+                    return None;
+                }
+
+                u32::from_str_radix(&code, 16).ok().and_then(char::from_u32)
+            }
+            _ => None,
+        }
+    }
+
+    /// Must be called after consuming a `'\n'`.
+    fn newline(&mut self) {
+        self.line += 1;
+        self.column = 0;
+    }
+
+    /// Marks the current position as the start of the next token.
+    fn begin(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column + 1;
+    }
+
+    fn span(&self) -> Span {
+        Span {
+            start: self.start,
+            end: self.current,
+        }
+    }
+
+    fn error(&mut self, kind: LexErrorKind) {
+        self.error_at(kind, self.span(), self.start_line, self.start_column);
+    }
+
+    /// Reports an error on part of a token rather than the whole of it.
+    fn error_at(&mut self, kind: LexErrorKind, span: Span, line: usize, column: usize) {
+        self.pending.push_back(Err(LexError {
+            kind,
+            line,
+            column,
+            span,
+            text: self.text[span.start..span.end].into(),
+        }));
+    }
+
     fn is(&mut self, c: char) -> bool {
         if self.at_end() {
             return false;
         }
 
-        if self.next() == c {
-            return true;
+        if self.current_char() != c {
+            return false;
         }
 
-        self.current += 1;
+        self.advance();
 
-        false
+        true
     }
 
     fn peek(&mut self) -> char {
@@ -209,39 +451,263 @@ impl Lexer {
             return '\0';
         }
 
-        self.next()
+        self.current_char()
     }
 
     fn peek_next(&mut self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
-        }
-
-        self.source.get(self.current + 1).copied().unwrap()
+        self.text[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
     fn advance(&mut self) -> char {
-        let cur = self.next();
-        self.current += 1;
+        let cur = self.current_char();
+        self.current += cur.len_utf8();
+        self.column += 1;
         cur
     }
 
     fn add(&mut self, ttype: TokenType) {
-        let lexeme: String = self
-            .source
-            .get(self.start..self.current)
-            .unwrap()
-            .iter()
-            .collect();
+        self.pending.push_back(Ok(self.token(ttype)));
+    }
 
-        self.tokens.push(Token::new(ttype, Some(lexeme), self.line))
+    fn token(&self, typ: TokenType) -> Token {
+        Token {
+            typ,
+            line: self.start_line,
+            column: self.start_column,
+            span: self.span(),
+            source: Rc::clone(&self.text),
+        }
     }
 
     fn at_end(&mut self) -> bool {
-        self.current >= self.source.len()
+        self.current >= self.text.len()
     }
 
-    fn next(&self) -> char {
-        self.source.get(self.current).copied().unwrap()
+    fn current_char(&self) -> char {
+        self.text[self.current..].chars().next().unwrap()
+    }
+}
+
+/// Tokens and errors come out in source order. Lexing carries on after an error, and the last item
+/// is always the `Eof` token.
+impl Iterator for Lexer {
+    type Item = Result<Token, LexError>;
+
+    /// Scans just far enough to produce the next item.
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && !self.at_end() {
+            self.begin();
+            self.scan();
+        }
+
+        if let Some(item) = self.pending.pop_front() {
+            return Some(item);
+        }
+
+        if self.done {
+            return None;
+        }
+
+        self.begin();
+        self.done = true;
+
+        Some(Ok(self.token(TokenType::Eof)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        lexer::{LexError, LexErrorKind, Lexer},
+        token::Span,
+        token_type::{Dialect, TokenType},
+    };
+
+    #[test]
+    fn check_errors() {
+        let (tokens, errors) = Lexer::new("var a = 1;\n  a @ 2 # 3;").parse();
+
+        // Lexing keeps going past bad characters.
+        assert_eq!(tokens.len(), 10);
+        assert_eq!(
+            errors,
+            vec![
+                LexError {
+                    kind: LexErrorKind::UnexpectedCharacter,
+                    line: 2,
+                    column: 5,
+                    span: Span { start: 15, end: 16 },
+                    text: "@".into(),
+                },
+                LexError {
+                    kind: LexErrorKind::UnexpectedCharacter,
+                    line: 2,
+                    column: 9,
+                    span: Span { start: 19, end: 20 },
+                    text: "#".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn check_strings() {
+        let (tokens, errors) = Lexer::new(
+            r#""a\tb\n\"c\" \\ \u{48}\u{1F600}" "two
+lines" x"#,
+        )
+        .parse();
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            tokens[0].typ,
+            TokenType::String("a\tb\n\"c\" \\ H\u{1F600}".into())
+        );
+        assert_eq!(tokens[1].typ, TokenType::String("two\nlines".into()));
+        assert_eq!((tokens[1].line, tokens[1].column), (1, 34));
+        assert_eq!((tokens[2].line, tokens[2].column), (2, 8));
+
+        let (_, errors) = Lexer::new("\"\\q \\u{zz} \\u{110000}\"\n  \"open\n").parse();
+
+        assert_eq!(
+            errors,
+            vec![
+                LexError {
+                    kind: LexErrorKind::InvalidEscape,
+                    line: 1,
+                    column: 2,
+                    span: Span { start: 1, end: 3 },
+                    text: "\\q".into(),
+                },
+                LexError {
+                    kind: LexErrorKind::InvalidEscape,
+                    line: 1,
+                    column: 5,
+                    span: Span { start: 4, end: 7 },
+                    text: "\\u{".into(),
+                },
+                LexError {
+                    kind: LexErrorKind::InvalidEscape,
+                    line: 1,
+                    column: 12,
+                    span: Span { start: 11, end: 21 },
+                    text: "\\u{110000}".into(),
+                },
+                LexError {
+                    kind: LexErrorKind::UnterminatedString,
+                    line: 2,
+                    column: 3,
+                    span: Span { start: 25, end: 26 },
+                    text: "\"".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn check_comments() {
+        let (tokens, errors) = Lexer::new("/* a /* nested\n */ b */ 1 /* open /* */").parse();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].line, 2);
+        assert_eq!(
+            errors,
+            vec![LexError {
+                kind: LexErrorKind::UnterminatedComment,
+                line: 2,
+                column: 12,
+                span: Span { start: 26, end: 28 },
+                text: "/*".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn check_identifiers() {
+        let (tokens, errors) = Lexer::new("my_var _x1 fn héllo").parse();
+
+        assert_eq!(
+            tokens.iter().map(|t| t.typ.clone()).collect::<Vec<_>>(),
+            vec![
+                TokenType::Id,
+                TokenType::Id,
+                TokenType::Fn,
+                TokenType::Id,
+                TokenType::Id,
+                TokenType::Eof,
+            ]
+        );
+        assert_eq!(errors[0].text, "é");
+    }
+
+    #[test]
+    fn check_numbers() {
+        let (tokens, errors) = Lexer::new("0x1F 0b1010 1e10 2.5e-3 1_000_000 7.").parse();
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|t| (t.typ.clone(), t.lexeme()))
+                .collect::<Vec<_>>(),
+            vec![
+                (TokenType::Number(31.0), "0x1F"),
+                (TokenType::Number(10.0), "0b1010"),
+                (TokenType::Number(1e10), "1e10"),
+                (TokenType::Number(2.5e-3), "2.5e-3"),
+                (TokenType::Number(1_000_000.0), "1_000_000"),
+                (TokenType::Number(7.0), "7"),
+                (TokenType::Dot, "."),
+                (TokenType::Eof, ""),
+            ]
+        );
+
+        let (tokens, errors) = Lexer::new("0x 1e 1e+ 0b102 1_ 12abc").parse();
+
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(
+            errors.iter().map(|e| e.text.as_str()).collect::<Vec<_>>(),
+            vec!["0x", "1e", "1e+", "0b102", "1_", "12abc"]
+        );
+        assert!(errors
+            .iter()
+            .all(|e| e.kind == LexErrorKind::MalformedNumber));
+    }
+
+    #[test]
+    fn check_iterator() {
+        let items = Lexer::new("a @ b")
+            .map(|item| item.map(|t| t.typ).map_err(|e| e.kind))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            items,
+            vec![
+                Ok(TokenType::Id),
+                Err(LexErrorKind::UnexpectedCharacter),
+                Ok(TokenType::Id),
+                Ok(TokenType::Eof),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_dialects() {
+        let types = |dialect| {
+            Lexer::new("fn fun")
+                .with_dialect(dialect)
+                .flatten()
+                .map(|t| t.typ)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            types(Dialect::Native),
+            vec![TokenType::Fn, TokenType::Id, TokenType::Eof]
+        );
+        assert_eq!(
+            types(Dialect::Reference),
+            vec![TokenType::Id, TokenType::Fn, TokenType::Eof]
+        );
     }
 }
@@ -0,0 +1,44 @@
+//! A tree-walking interpreter for Lox, following the jlox half of Crafting Interpreters.
+//!
+//! Hosts embed it through [`Session`], which keeps globals alive between calls to
+//! [`Session::eval`] so a script can be loaded once and its functions called from Rust later:
+//!
+//! ```
+//! use crafting_interpreters::{Session, Value};
+//!
+//! let mut session = Session::new();
+//! session.set_global("base", Value::Number(40.0));
+//! session.eval("fn answer(n) { return base + n; }").unwrap();
+//!
+//! let answer = session.call_function("answer", vec![Value::Number(2.0)]).unwrap();
+//! assert_eq!(answer, Value::Number(42.0));
+//! ```
+
+mod callable;
+mod class;
+mod diagnostics;
+mod environment;
+mod function;
+mod gen_ast;
+mod interpreter;
+mod lexer;
+mod lox;
+mod parser;
+mod printer;
+mod resolver;
+mod session;
+mod stack;
+mod token;
+mod token_type;
+mod value;
+
+pub use interpreter::{Interpreter, Output, RuntimeError};
+pub use lexer::{LexError, LexErrorKind, Lexer};
+pub use lox::{Lox, LoxError};
+pub use parser::ParseError;
+pub use resolver::ResolveError;
+pub use session::Session;
+pub use stack::DEFAULT_STACK_LIMIT;
+pub use token::{Span, Token};
+pub use token_type::{Dialect, TokenType};
+pub use value::Value;
@@ -1,56 +1,157 @@
-use std::{
-    fs::read_to_string,
-    io::{self, BufRead, Write},
+use std::{error::Error, fmt::Display, rc::Rc};
+
+use crate::{
+    diagnostics::{Diagnostic, Label},
+    interpreter::{Interpreter, RuntimeError},
+    lexer::{LexError, Lexer},
+    parser::{ParseError, Parser},
+    resolver::{ResolveError, Resolver},
+    token::Token,
+    token_type::{Dialect, TokenType},
+    value::Value,
 };
 
-use crate::{interpreter::Interpreter, lexer::Lexer, parser::Parser};
+/// Everything that can stop a program, from the first stage that failed.
+#[derive(Debug)]
+pub enum LoxError {
+    Lex(Vec<LexError>),
+    Parse(Vec<ParseError>),
+    Resolve(Vec<ResolveError>),
+    Runtime(RuntimeError),
+}
+
+impl LoxError {
+    /// The static errors in jlox's own format, e.g. `[line 3] Error at 'x': Expect ';'.`.
+    pub fn jlox_messages(&self) -> Vec<String> {
+        let at = |token: &Token| match token.typ {
+            TokenType::Eof => " at end".to_string(),
+            _ => format!(" at '{}'", token.lexeme()),
+        };
+
+        match self {
+            Self::Lex(errs) => errs
+                .iter()
+                .map(|e| format!("[line {}] Error: {}", e.line, e.kind))
+                .collect(),
+            Self::Parse(errs) => errs
+                .iter()
+                .map(|e| format!("[line {}] Error{}: {}", e.token.line, at(&e.token), e.msg))
+                .collect(),
+            Self::Resolve(errs) => errs
+                .iter()
+                .map(|e| format!("[line {}] Error{}: {}", e.token.line, at(&e.token), e.msg))
+                .collect(),
+            Self::Runtime(_) => vec![],
+        }
+    }
+}
+
+impl Display for LoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Runtime(err) => write!(f, "{}\n[line {}]", err.msg, err.token.line),
+            _ => write!(f, "{}", self.jlox_messages().join("\n")),
+        }
+    }
+}
+
+impl Error for LoxError {}
+
+impl From<RuntimeError> for LoxError {
+    fn from(err: RuntimeError) -> Self {
+        Self::Runtime(err)
+    }
+}
 
 pub struct Lox;
 
 impl Lox {
-    pub fn run_file(f: String) -> io::Result<()> {
-        let src = read_to_string(f)?;
+    /// Runs `src` to completion, stopping at the first stage that reports errors.
+    pub fn run(
+        interpreter: &mut Interpreter,
+        src: &str,
+        dialect: Dialect,
+    ) -> Result<Option<Value>, LoxError> {
+        // The parser pulls tokens straight from the lexer. Lexer errors are set aside as they turn
+        // up, and take priority over any parse errors they may have caused.
+        let mut lex_errors = vec![];
+        let tokens = Lexer::new(src)
+            .with_dialect(dialect)
+            .filter_map(|item| item.map_err(|e| lex_errors.push(e)).ok());
+        let stmts = Parser::new(tokens)
+            .with_stack_limit(interpreter.stack_limit())
+            .parse();
 
-        Self::run(&src).unwrap();
+        if !lex_errors.is_empty() {
+            return Err(LoxError::Lex(lex_errors));
+        }
 
-        Ok(())
-    }
+        let stmts = stmts.map_err(LoxError::Parse)?;
 
-    pub fn run_prompt() -> io::Result<()> {
-        let sti = io::stdin();
-        let mut sto = io::stdout();
+        Resolver::default()
+            .resolve_program(&stmts)
+            .map_err(LoxError::Resolve)?;
 
-        loop {
-            print!("> ");
-            sto.flush()?;
+        interpreter.interpret(&stmts).map_err(LoxError::Runtime)
+    }
 
-            match sti.lock().lines().next() {
-                Some(Ok(s)) => Self::run(&s).ok(),
-                _ => break,
-            };
+    /// Prints `err` as diagnostics. `file` is only used to label them.
+    pub fn report(file: &str, src: &str, err: LoxError) {
+        let diags = match err {
+            LoxError::Lex(errs) => Self::lex_errors(src.into(), errs),
+            LoxError::Parse(errs) => Self::parse_errors(errs),
+            LoxError::Resolve(errs) => Self::resolve_errors(errs),
+            LoxError::Runtime(err) => vec![Self::runtime_error(err)],
+        };
+
+        for diag in diags {
+            diag.emit(file);
         }
+    }
 
-        Ok(())
+    fn lex_errors(src: Rc<str>, errs: Vec<LexError>) -> Vec<Diagnostic> {
+        errs.into_iter()
+            .map(|err| {
+                Diagnostic::error(err.kind.to_string())
+                    .with_label(Label::primary(&src, err.span, ""))
+            })
+            .collect()
     }
 
-    pub fn run(src: &str) -> Result<(), ()> {
-        let tokens = Lexer::new(src).parse()?;
-        let tree = Parser::new(&tokens).parse();
-        // for t in tokens {
-        //     println!("{:?}", t)
-        // }
+    fn parse_errors(errs: Vec<ParseError>) -> Vec<Diagnostic> {
+        errs.into_iter()
+            .map(|err| Self::error_at(&err.token, err.msg))
+            .collect()
+    }
+
+    fn resolve_errors(errs: Vec<ResolveError>) -> Vec<Diagnostic> {
+        errs.into_iter()
+            .map(|err| {
+                let mut diag = Self::error_at(&err.token, err.msg);
+
+                if let Some(declared) = err.declared {
+                    let msg = format!("'{}' declared here", err.token.lexeme());
+                    diag = diag.with_label(Label::secondary(&declared.source, declared.span, msg));
+                }
 
-        dbg!(&tree);
-        Interpreter::interpret(*tree);
+                if let Some(help) = err.help {
+                    diag = diag.with_help(help);
+                }
 
-        Ok(())
+                diag
+            })
+            .collect()
     }
 
-    pub fn error(line: usize, msg: String) {
-        Self::report(line, "".into(), msg);
+    fn error_at(token: &Token, msg: String) -> Diagnostic {
+        let label = match token.typ {
+            TokenType::Eof => "at end",
+            _ => "",
+        };
+        Diagnostic::error(msg).with_label(Label::primary(&token.source, token.span, label))
     }
 
-    pub fn report(line: usize, whr: String, msg: String) {
-        println!("[line {line}] Error {whr}: {msg}");
+    fn runtime_error(err: RuntimeError) -> Diagnostic {
+        Diagnostic::error(err.msg).at(&err.token)
     }
 }
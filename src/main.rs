@@ -1,23 +1,108 @@
-mod gen_ast;
-mod interpreter;
-mod lexer;
-mod lox;
-mod parser;
-mod printer;
-mod token;
-mod token_type;
+mod conformance;
 
-use std::env;
+use std::{
+    env, fs,
+    io::{self, BufRead, Write},
+    path::Path,
+    process, thread,
+};
 
-use lox::Lox;
+use crafting_interpreters::{Dialect, Interpreter, Lox, LoxError, DEFAULT_STACK_LIMIT};
 
-fn main() -> std::io::Result<()> {
-    let mut args = env::args();
+const USAGE: &str = "Usage: lox [--dialect=native|reference] [script | test [dir]]";
 
-    args.next();
+/// Scripts run on a thread of their own with this much stack, enough for tens of thousands of
+/// nested calls in a release build and thousands in a debug one.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
 
-    match args.next() {
-        Some(f) => Lox::run_file(f),
-        None => Lox::run_prompt(),
+/// How much of that scripts may use, leaving the rest for whatever runs between checks.
+const STACK_LIMIT: usize = STACK_SIZE - DEFAULT_STACK_LIMIT;
+
+fn main() -> io::Result<()> {
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)?
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+fn run() -> io::Result<()> {
+    let mut dialect = Dialect::default();
+    let mut args = vec![];
+
+    for arg in env::args().skip(1) {
+        match arg.strip_prefix("--dialect=") {
+            Some(name) => {
+                dialect = name.parse().unwrap_or_else(|err| {
+                    println!("{err}\n{USAGE}");
+                    process::exit(64);
+                })
+            }
+            None => args.push(arg),
+        }
+    }
+
+    match args.as_slice() {
+        [cmd] if cmd == "test" => run_tests(".", dialect),
+        [cmd, dir] if cmd == "test" => run_tests(dir, dialect),
+        [f] => run_file(f, dialect),
+        [] => run_prompt(dialect),
+        _ => {
+            println!("{USAGE}");
+            process::exit(64);
+        }
+    }
+}
+
+fn run_file(f: &str, dialect: Dialect) -> io::Result<()> {
+    let src = fs::read_to_string(f)?;
+
+    let mut interpreter = Interpreter::default().with_stack_limit(STACK_LIMIT);
+
+    if let Err(err) = Lox::run(&mut interpreter, &src, dialect) {
+        let code = exit_code(&err);
+        Lox::report(f, &src, err);
+        process::exit(code);
+    }
+
+    Ok(())
+}
+
+fn run_prompt(dialect: Dialect) -> io::Result<()> {
+    let sti = io::stdin();
+    let mut sto = io::stdout();
+    let mut interpreter = Interpreter::default().with_stack_limit(STACK_LIMIT);
+
+    loop {
+        print!("> ");
+        sto.flush()?;
+
+        match sti.lock().lines().next() {
+            Some(Ok(s)) => match Lox::run(&mut interpreter, &s, dialect) {
+                Ok(Some(value)) => println!("{value}"),
+                Ok(None) => {}
+                Err(err) => Lox::report("<repl>", &s, err),
+            },
+            _ => break,
+        };
+    }
+
+    Ok(())
+}
+
+/// Runs every `.lox` file under `dir` against the expectations written in its comments.
+fn run_tests(dir: &str, dialect: Dialect) -> io::Result<()> {
+    if !conformance::run_dir(Path::new(dir), dialect)? {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Follows jlox, which borrows from sysexits.h: 65 for bad input, 70 for a failure while running.
+fn exit_code(err: &LoxError) -> i32 {
+    match err {
+        LoxError::Runtime(_) => 70,
+        _ => 65,
     }
 }
@@ -1,52 +1,485 @@
-//! expression     → equality ;
+//! program        → declaration* EOF ;
+//! declaration    → classDecl | funDecl | varDecl | statement ;
+//! classDecl      → "class" IDENTIFIER ( "<" IDENTIFIER )? "{" function* "}" ;
+//! funDecl        → "fn" function ;             (`fun` in the reference dialect)
+//! function       → IDENTIFIER "(" parameters? ")" block ;
+//! parameters     → IDENTIFIER ( "," IDENTIFIER )* ;
+//! varDecl        → "var" IDENTIFIER ( "=" expression )? ";" ;
+//! statement      → exprStmt | forStmt | ifStmt | printStmt | returnStmt | whileStmt | block ;
+//! exprStmt       → expression ";" ;
+//! forStmt        → "for" "(" ( varDecl | exprStmt | ";" ) expression? ";" expression? ")" statement ;
+//! ifStmt         → "if" "(" expression ")" statement ( "else" statement )? ;
+//! printStmt      → "print" expression ";" ;
+//! returnStmt     → "return" expression? ";" ;
+//! whileStmt      → "while" "(" expression ")" statement ;
+//! block          → "{" declaration* "}" ;
+//! expression     → assignment ;
+//! assignment     → ( call "." )? IDENTIFIER "=" assignment | logic_or ;
+//! logic_or       → logic_and ( "or" logic_and )* ;
+//! logic_and      → equality ( "and" equality )* ;
 //! equality       → comparison ( ( "!=" | "==" ) comparison )* ;
 //! comparison     → term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
 //! term           → factor ( ( "-" | "+" ) factor )* ;
 //! factor         → unary ( ( "/" | "*" ) unary )* ;
-//! unary          → ( "!" | "-" ) unary | primary ;
-//! primary        → NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")" ;
+//! unary          → ( "!" | "-" ) unary | call ;
+//! call           → primary ( "(" arguments? ")" | "." IDENTIFIER )* ;
+//! arguments      → expression ( "," expression )* ;
+//! primary        → NUMBER | STRING | "true" | "false" | "nil" | "this" | "(" expression ")" | IDENTIFIER
+//!                | "super" "." IDENTIFIER ;
+
+use std::{cell::Cell, rc::Rc};
 
 use crate::{
-    gen_ast::{Binary, Expr, Grouping, Literal, Unary},
+    gen_ast::{
+        Assign, Binary, Block, Call, Class, Expr, Expression, Function, Get, Grouping, If, Literal,
+        Logical, Print, Return, Set, Stmt, Super, This, Unary, Var, Variable, While,
+    },
+    stack::{StackGuard, DEFAULT_STACK_LIMIT},
     token::Token,
     token_type::TokenType,
 };
 
 #[derive(Debug)]
-pub struct Parser<'p> {
-    tokens: &'p [Token],
-    current: usize,
+pub struct ParseError {
+    pub token: Token,
+    pub msg: String,
+}
+
+/// Pulls tokens from any iterator, one at a time as the grammar needs them, so the whole token
+/// stream never has to exist at once.
+#[derive(Debug)]
+pub struct Parser<I: Iterator<Item = Token>> {
+    tokens: I,
+    /// The next token, i.e. one token of lookahead. Stays on `Eof` once that is reached.
+    current: Token,
+    /// Text of the `///` comments directly before `current`, one line per comment.
+    doc: Option<String>,
+    previous: Option<Token>,
+    errors: Vec<ParseError>,
+    stack: StackGuard,
+    /// Set once nesting has used up the stack. There is no sensible place to resume from then, so
+    /// the parse stops rather than reporting every enclosing block as unterminated.
+    too_deep: bool,
 }
 
-impl<'p> Parser<'p> {
-    pub fn new(tokens: &'p [Token]) -> Self {
-        Self { tokens, current: 0 }
+impl<I: Iterator<Item = Token>> Parser<I> {
+    /// The tokens should end with `Eof`, as the lexer's do. One is made up if they don't.
+    pub fn new(tokens: impl IntoIterator<IntoIter = I>) -> Self {
+        let mut tokens = tokens.into_iter();
+        let (current, doc) = Self::next_token(&mut tokens, 1);
+
+        Self {
+            tokens,
+            current,
+            doc,
+            previous: None,
+            errors: vec![],
+            stack: StackGuard::new(DEFAULT_STACK_LIMIT),
+            too_deep: false,
+        }
     }
 
-    pub fn parse(&mut self) -> Box<Expr> {
-        self.expression()
+    /// How many bytes of stack nested code may take to parse before it is rejected.
+    pub fn with_stack_limit(mut self, bytes: usize) -> Self {
+        self.stack = StackGuard::new(bytes);
+        self
     }
 
-    fn expression(&mut self) -> Box<Expr> {
-        self.equality()
+    /// Parses the whole program. Syntax errors don't stop the parse, so every one of them in the
+    /// source is returned together. The exception is nesting too deeply, which ends it.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let mut stmts = vec![];
+        let outermost = self.stack.start();
+
+        while !self.at_end() {
+            match self.declaration() {
+                Ok(stmt) => stmts.extend(stmt),
+                Err(err) => {
+                    self.errors.push(err);
+                    break;
+                }
+            }
+        }
+
+        if outermost {
+            self.stack.stop();
+        }
+
+        if self.errors.is_empty() {
+            Ok(stmts)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// Records a failed declaration and skips to the next statement boundary, which is where
+    /// panic mode ends and parsing picks up again. Only nesting too deeply is passed on, all the
+    /// way out of the parse.
+    fn declaration(&mut self) -> Result<Option<Stmt>, ParseError> {
+        match self.try_declaration() {
+            Ok(stmt) => Ok(Some(stmt)),
+            Err(err) if self.too_deep => Err(err),
+            Err(err) => {
+                self.errors.push(err);
+                self.synchronize();
+                Ok(None)
+            }
+        }
+    }
+
+    /// declaration    → classDecl | funDecl | varDecl | statement ;
+    fn try_declaration(&mut self) -> Result<Stmt, ParseError> {
+        self.check_stack()?;
+
+        let doc = self.doc.clone();
+
+        if self.one_of(&[TokenType::Class]) {
+            return self.class_declaration(doc);
+        }
+
+        if self.one_of(&[TokenType::Fn]) {
+            return Ok(Stmt::Function(self.function("function", doc)?));
+        }
+
+        if self.one_of(&[TokenType::Var]) {
+            return self.var_declaration(doc);
+        }
+
+        self.statement()
+    }
+
+    /// classDecl      → "class" IDENTIFIER ( "<" IDENTIFIER )? "{" function* "}" ;
+    fn class_declaration(&mut self, doc: Option<String>) -> Result<Stmt, ParseError> {
+        let name = self.consume_id("Expect class name.")?;
+
+        let superclass = if self.one_of(&[TokenType::Less]) {
+            Some(Variable {
+                name: self.consume_id("Expect superclass name.")?,
+                depth: Cell::new(None),
+            })
+        } else {
+            None
+        };
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = vec![];
+
+        while !self.check(&TokenType::RightBrace) && !self.at_end() {
+            let doc = self.doc.clone();
+            methods.push(self.function("method", doc)?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+
+        Ok(Stmt::Class(Class {
+            name,
+            superclass,
+            methods,
+            doc,
+        }))
+    }
+
+    /// function       → IDENTIFIER "(" parameters? ")" block ;
+    fn function(&mut self, kind: &str, doc: Option<String>) -> Result<Function, ParseError> {
+        let name = self.consume_id(&format!("Expect {kind} name."))?;
+        self.consume(
+            TokenType::LeftParen,
+            &format!("Expect '(' after {kind} name."),
+        )?;
+
+        let mut params = vec![];
+
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if params.len() >= 255 {
+                    let err =
+                        self.error(self.peek().clone(), "Can't have more than 255 parameters.");
+                    self.errors.push(err);
+                }
+
+                params.push(self.consume_id("Expect parameter name.")?);
+
+                if !self.one_of(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {kind} body."),
+        )?;
+
+        let body = Rc::new(self.block()?);
+
+        Ok(Function {
+            name,
+            params,
+            body,
+            doc,
+        })
+    }
+
+    /// varDecl        → "var" IDENTIFIER ( "=" expression )? ";" ;
+    fn var_declaration(&mut self, doc: Option<String>) -> Result<Stmt, ParseError> {
+        let name = self.consume_id("Expect variable name.")?;
+
+        let initializer = if self.one_of(&[TokenType::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        )?;
+
+        Ok(Stmt::Var(Var {
+            name,
+            initializer,
+            doc,
+        }))
+    }
+
+    /// statement      → exprStmt | forStmt | ifStmt | printStmt | returnStmt | whileStmt | block ;
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        self.check_stack()?;
+
+        if self.one_of(&[TokenType::For]) {
+            return self.for_statement();
+        }
+
+        if self.one_of(&[TokenType::If]) {
+            return self.if_statement();
+        }
+
+        if self.one_of(&[TokenType::Print]) {
+            return self.print_statement();
+        }
+
+        if self.one_of(&[TokenType::Return]) {
+            return self.return_statement();
+        }
+
+        if self.one_of(&[TokenType::While]) {
+            return self.while_statement();
+        }
+
+        if self.one_of(&[TokenType::LeftBrace]) {
+            return Ok(Stmt::Block(Block {
+                stmts: self.block()?,
+            }));
+        }
+
+        self.expression_statement()
+    }
+
+    /// forStmt        → "for" "(" ( varDecl | exprStmt | ";" ) expression? ";" expression? ")" statement ;
+    ///
+    /// There is no `For` node, the loop is desugared into a `while` wrapped in blocks.
+    fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+        let initializer = if self.one_of(&[TokenType::Semicolon]) {
+            None
+        } else if self.one_of(&[TokenType::Var]) {
+            Some(self.var_declaration(None)?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let cond = if self.check(&TokenType::Semicolon) {
+            Box::new(Expr::Literal(Literal {
+                value: TokenType::True,
+            }))
+        } else {
+            self.expression()?
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
+
+        let increment = if self.check(&TokenType::RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        let mut body = self.statement()?;
+
+        if let Some(expr) = increment {
+            body = Stmt::Block(Block {
+                stmts: vec![body, Stmt::Expression(Expression { expr })],
+            });
+        }
+
+        body = Stmt::While(While {
+            cond,
+            body: Box::new(body),
+        });
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(Block {
+                stmts: vec![initializer, body],
+            });
+        }
+
+        Ok(body)
+    }
+
+    /// ifStmt         → "if" "(" expression ")" statement ( "else" statement )? ;
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let cond = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch = Box::new(self.statement()?);
+        let else_branch = if self.one_of(&[TokenType::Else]) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If(If {
+            cond,
+            then_branch,
+            else_branch,
+        }))
+    }
+
+    /// printStmt      → "print" expression ";" ;
+    fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expr = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
+        Ok(Stmt::Print(Print { expr }))
+    }
+
+    /// returnStmt     → "return" expression? ";" ;
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+
+        let value = if self.check(&TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
+
+        Ok(Stmt::Return(Return { keyword, value }))
+    }
+
+    /// whileStmt      → "while" "(" expression ")" statement ;
+    fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let cond = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::While(While { cond, body }))
+    }
+
+    /// exprStmt       → expression ";" ;
+    fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expr = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
+        Ok(Stmt::Expression(Expression { expr }))
+    }
+
+    /// block          → "{" declaration* "}" ;
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut stmts = vec![];
+
+        while !self.check(&TokenType::RightBrace) && !self.at_end() {
+            stmts.extend(self.declaration()?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
+
+        Ok(stmts)
+    }
+
+    fn expression(&mut self) -> Result<Box<Expr>, ParseError> {
+        self.assignment()
+    }
+
+    /// assignment     → ( call "." )? IDENTIFIER "=" assignment | logic_or ;
+    fn assignment(&mut self) -> Result<Box<Expr>, ParseError> {
+        self.check_stack()?;
+
+        let expr = self.or()?;
+
+        if self.one_of(&[TokenType::Equal]) {
+            let equals = self.previous();
+            let value = self.assignment()?;
+
+            return match *expr {
+                Expr::Variable(Variable { name, .. }) => Ok(Box::new(Expr::Assign(Assign {
+                    name,
+                    value,
+                    depth: Cell::new(None),
+                }))),
+                Expr::Get(Get { object, name }) => Ok(Box::new(Expr::Set(Set {
+                    object,
+                    name,
+                    value,
+                }))),
+                // The parser isn't confused here, so report without entering panic mode.
+                expr => {
+                    let err = self.error(equals, "Invalid assignment target.");
+                    self.errors.push(err);
+                    Ok(Box::new(expr))
+                }
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /// logic_or       → logic_and ( "or" logic_and )* ;
+    fn or(&mut self) -> Result<Box<Expr>, ParseError> {
+        let mut left = self.and()?;
+
+        while self.one_of(&[TokenType::Or]) {
+            let op = self.previous();
+            let right = self.and()?;
+            left = Box::new(Expr::Logical(Logical { left, op, right }))
+        }
+
+        Ok(left)
+    }
+
+    /// logic_and      → equality ( "and" equality )* ;
+    fn and(&mut self) -> Result<Box<Expr>, ParseError> {
+        let mut left = self.equality()?;
+
+        while self.one_of(&[TokenType::And]) {
+            let op = self.previous();
+            let right = self.equality()?;
+            left = Box::new(Expr::Logical(Logical { left, op, right }))
+        }
+
+        Ok(left)
     }
 
     /// equality       → comparison ( ( "!=" | "==" ) comparison )* ;
-    fn equality(&mut self) -> Box<Expr> {
-        let mut left = self.comparison();
+    fn equality(&mut self) -> Result<Box<Expr>, ParseError> {
+        let mut left = self.comparison()?;
 
         while self.one_of(&[TokenType::BangEqual, TokenType::EqualEqual]) {
             let op = self.previous();
-            let right = self.comparison();
+            let right = self.comparison()?;
             left = Box::new(Expr::Binary(Binary { left, op, right }))
         }
 
-        left
+        Ok(left)
     }
 
     /// comparison     → term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
-    fn comparison(&mut self) -> Box<Expr> {
-        let mut left = self.term();
+    fn comparison(&mut self) -> Result<Box<Expr>, ParseError> {
+        let mut left = self.term()?;
 
         while self.one_of(&[
             TokenType::Greater,
@@ -55,57 +488,108 @@ impl<'p> Parser<'p> {
             TokenType::LessEqual,
         ]) {
             let op = self.previous();
-            let right = self.term();
+            let right = self.term()?;
             left = Box::new(Expr::Binary(Binary { left, op, right }));
         }
 
-        left
+        Ok(left)
     }
 
     /// term           → factor ( ( "-" | "+" ) factor )* ;
-    fn term(&mut self) -> Box<Expr> {
-        let mut left = self.factor();
+    fn term(&mut self) -> Result<Box<Expr>, ParseError> {
+        let mut left = self.factor()?;
 
         while self.one_of(&[TokenType::Minus, TokenType::Plus]) {
             let op = self.previous();
-            let right = self.factor();
+            let right = self.factor()?;
             left = Box::new(Expr::Binary(Binary { left, op, right }))
         }
 
-        left
+        Ok(left)
     }
 
     /// factor         → unary ( ( "/" | "*" ) unary )* ;
-    fn factor(&mut self) -> Box<Expr> {
-        let mut left = self.unary();
+    fn factor(&mut self) -> Result<Box<Expr>, ParseError> {
+        let mut left = self.unary()?;
 
         while self.one_of(&[TokenType::Slash, TokenType::Star]) {
             let op = self.previous();
-            let right = self.unary();
+            let right = self.unary()?;
             left = Box::new(Expr::Binary(Binary { left, op, right }))
         }
 
-        left
+        Ok(left)
     }
 
-    /// unary          → ( "!" | "-" ) unary | primary ;
-    fn unary(&mut self) -> Box<Expr> {
+    /// unary          → ( "!" | "-" ) unary | call ;
+    fn unary(&mut self) -> Result<Box<Expr>, ParseError> {
+        self.check_stack()?;
+
         if self.one_of(&[TokenType::Bang, TokenType::Minus]) {
             let op = self.previous();
-            let right = self.unary();
-            return Box::new(Expr::Unary(Unary { op, right }));
+            let right = self.unary()?;
+            return Ok(Box::new(Expr::Unary(Unary { op, right })));
+        }
+
+        self.call()
+    }
+
+    /// call           → primary ( "(" arguments? ")" | "." IDENTIFIER )* ;
+    fn call(&mut self) -> Result<Box<Expr>, ParseError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.one_of(&[TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.one_of(&[TokenType::Dot]) {
+                let name = self.consume_id("Expect property name after '.'.")?;
+                expr = Box::new(Expr::Get(Get { object: expr, name }));
+            } else {
+                break;
+            }
         }
 
-        self.primary().unwrap()
+        Ok(expr)
     }
 
-    /// primary        → NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")" ;
-    fn primary(&mut self) -> Option<Box<Expr>> {
-        match self.tokens.get(self.current).cloned().unwrap().typ {
+    /// arguments      → expression ( "," expression )* ;
+    fn finish_call(&mut self, callee: Box<Expr>) -> Result<Box<Expr>, ParseError> {
+        let mut args = vec![];
+
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if args.len() >= 255 {
+                    let err =
+                        self.error(self.peek().clone(), "Can't have more than 255 arguments.");
+                    self.errors.push(err);
+                }
+
+                args.push(*self.expression()?);
+
+                if !self.one_of(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
+
+        Ok(Box::new(Expr::Call(Call {
+            callee,
+            paren,
+            args,
+        })))
+    }
+
+    /// primary        → NUMBER | STRING | "true" | "false" | "nil" | "this" | "(" expression ")" | IDENTIFIER
+    ///                | "super" "." IDENTIFIER ;
+    fn primary(&mut self) -> Result<Box<Expr>, ParseError> {
+        match self.peek().typ.clone() {
             TokenType::LeftParen => {
-                let expr = self.expression();
-                self.consume(TokenType::RightParen);
-                Some(Box::new(Expr::Grouping(Grouping { expr })))
+                self.advance();
+                let expr = self.expression()?;
+                self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
+                Ok(Box::new(Expr::Grouping(Grouping { expr })))
             }
             x @ (TokenType::True
             | TokenType::False
@@ -113,20 +597,62 @@ impl<'p> Parser<'p> {
             | TokenType::Number(_)
             | TokenType::String(_)) => {
                 self.advance();
-                Some(Box::new(Expr::Literal(Literal { value: x })))
+                Ok(Box::new(Expr::Literal(Literal { value: x })))
             }
-            _ => {
-                println!("WTF");
-                None
+            TokenType::Super => {
+                let keyword = self.advance();
+                self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+                let method = self.consume_id("Expect superclass method name.")?;
+
+                Ok(Box::new(Expr::Super(Super {
+                    keyword,
+                    method,
+                    depth: Cell::new(None),
+                })))
             }
+            TokenType::This => Ok(Box::new(Expr::This(This {
+                keyword: self.advance(),
+                depth: Cell::new(None),
+            }))),
+            TokenType::Id => Ok(Box::new(Expr::Variable(Variable {
+                name: self.advance(),
+                depth: Cell::new(None),
+            }))),
+            _ => Err(self.error(self.peek().clone(), "Expect expression.")),
         }
     }
 
-    fn consume(&mut self, ty: TokenType) {
+    fn consume(&mut self, ty: TokenType, msg: &str) -> Result<Token, ParseError> {
         if self.check(&ty) {
-            self.advance();
-        } else {
-            println!("WTF")
+            return Ok(self.advance());
+        }
+
+        Err(self.error(self.peek().clone(), msg))
+    }
+
+    fn consume_id(&mut self, msg: &str) -> Result<Token, ParseError> {
+        if let TokenType::Id = self.peek().typ {
+            return Ok(self.advance());
+        }
+
+        Err(self.error(self.peek().clone(), msg))
+    }
+
+    /// Every rule that can nest calls this first, so deeply nested code is rejected instead of
+    /// overflowing the stack.
+    fn check_stack(&mut self) -> Result<(), ParseError> {
+        if self.stack.exhausted() {
+            self.too_deep = true;
+            return Err(self.error(self.peek().clone(), "Too much nesting."));
+        }
+
+        Ok(())
+    }
+
+    fn error(&self, token: Token, msg: &str) -> ParseError {
+        ParseError {
+            token,
+            msg: msg.into(),
         }
     }
 
@@ -134,7 +660,7 @@ impl<'p> Parser<'p> {
         self.advance();
 
         while !self.at_end() {
-            if self.previous().typ == TokenType::Eof {
+            if self.previous().typ == TokenType::Semicolon {
                 return;
             }
 
@@ -172,25 +698,143 @@ impl<'p> Parser<'p> {
             return false;
         }
 
-        return &self.peek().typ == ty;
+        &self.peek().typ == ty
     }
 
     fn advance(&mut self) -> Token {
         if !self.at_end() {
-            self.current += 1
+            let (next, doc) = Self::next_token(&mut self.tokens, self.current.line);
+
+            self.doc = doc;
+            self.previous = Some(std::mem::replace(&mut self.current, next));
         }
         self.previous()
     }
 
+    /// Pulls the next token that isn't a doc comment, along with the text of the doc comments
+    /// skipped to reach it.
+    fn next_token(tokens: &mut I, line: usize) -> (Token, Option<String>) {
+        let mut doc: Option<String> = None;
+
+        for token in tokens.by_ref() {
+            if token.typ != TokenType::DocComment {
+                return (token, doc);
+            }
+
+            let text = &token.lexeme()["///".len()..];
+            let text = text
+                .strip_prefix(' ')
+                .unwrap_or(text)
+                .trim_end_matches('\r');
+
+            doc = Some(match doc {
+                Some(doc) => format!("{doc}\n{text}"),
+                None => text.into(),
+            });
+        }
+
+        (Token::new(TokenType::Eof, None, line), doc)
+    }
+
     fn at_end(&self) -> bool {
         self.peek().typ == TokenType::Eof
     }
 
     fn peek(&self) -> &Token {
-        self.tokens.get(self.current).unwrap()
+        &self.current
     }
 
     fn previous(&self) -> Token {
-        self.tokens.get(self.current - 1).cloned().unwrap()
+        self.previous.clone().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{gen_ast::Stmt, lexer::Lexer, parser::Parser};
+
+    #[test]
+    fn check_recovery() {
+        let src = "
+            var = 1;
+            print (2;
+            fn f( { }
+            var ok = 3;
+            { print ok print ok; }
+            1 = 2;
+            class C { 4 }
+        ";
+        let (tokens, _) = Lexer::new(src).parse();
+        let errs = Parser::new(tokens).parse().unwrap_err();
+
+        assert_eq!(
+            errs.iter()
+                .map(|e| (e.token.line, e.msg.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (2, "Expect variable name."),
+                (3, "Expect ')' after expression."),
+                (4, "Expect parameter name."),
+                (6, "Expect ';' after value."),
+                (7, "Invalid assignment target."),
+                (8, "Expect method name."),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_nesting_limit() {
+        // Each of these overflows the stack if nothing stops the parser first.
+        let srcs = [
+            format!("print {}1{};", "(".repeat(20_000), ")".repeat(20_000)),
+            format!("print {}1;", "-".repeat(50_000)),
+            format!("{}{}", "{".repeat(20_000), "}".repeat(20_000)),
+        ];
+
+        for src in srcs {
+            let errs = Parser::new(Lexer::new(src).flatten()).parse().unwrap_err();
+            assert_eq!(errs.len(), 1);
+            assert_eq!(errs[0].msg, "Too much nesting.");
+        }
+    }
+
+    #[test]
+    fn check_doc_comments() {
+        let src = "
+            /// A point.
+            ///
+            /// Has two fields.
+            class Point {
+                /// Builds one.
+                init(x, y) { /* not a doc */ this.x = x; }
+            }
+            //// Not a doc either.
+            fn f() {}
+            /// Origin.
+            var origin = Point(0, 0);
+        ";
+        let stmts = Parser::new(Lexer::new(src).flatten()).parse().unwrap();
+
+        let docs = stmts
+            .iter()
+            .map(|stmt| match stmt {
+                Stmt::Class(c) => (c.doc.clone(), c.methods[0].doc.clone()),
+                Stmt::Function(f) => (f.doc.clone(), None),
+                Stmt::Var(v) => (v.doc.clone(), None),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            docs,
+            vec![
+                (
+                    Some("A point.\n\nHas two fields.".into()),
+                    Some("Builds one.".into())
+                ),
+                (None, None),
+                (Some("Origin.".into()), None),
+            ]
+        );
     }
 }
@@ -1,6 +1,9 @@
 use crate::{
-    gen_ast::{Binary, Expr, Grouping, Literal, Unary, Visitor},
-    token_type::TokenType,
+    gen_ast::{
+        Assign, Binary, Call, Expr, ExprVisitor, Get, Grouping, Literal, Logical, Set, Super, This,
+        Unary, Variable,
+    },
+    token_type::Dialect,
 };
 
 #[derive(Debug, Clone, Copy)]
@@ -8,29 +11,29 @@ pub struct Printer {}
 
 impl Printer {
     #[allow(unused)]
-    fn print(self, expr: Expr) -> Result<String, ()> {
-        expr.accept(self)
+    fn print(mut self, expr: Expr) -> Result<String, ()> {
+        expr.accept(&mut self)
     }
 
     fn parenthesize(
-        &self,
+        &mut self,
         op: Option<String>,
-        left: Option<Box<Expr>>,
-        right: Option<Box<Expr>>,
+        left: Option<&Expr>,
+        right: Option<&Expr>,
     ) -> Result<String, ()> {
         let mut string = String::new();
 
         string.push('(');
-        string.push_str(&op.unwrap_or_else(|| "".to_string()));
+        string.push_str(&op.unwrap_or_default());
 
         if let Some(x) = left {
             string.push(' ');
-            string.push_str(&x.accept(*self)?);
+            string.push_str(&x.accept(self)?);
         }
 
         if let Some(x) = right {
             string.push(' ');
-            string.push_str(&x.accept(*self)?);
+            string.push_str(&x.accept(self)?);
         }
 
         string.push(')');
@@ -39,24 +42,85 @@ impl Printer {
     }
 }
 
-impl Visitor<String> for Printer {
-    fn visit_binary(&self, expr: Binary) -> Result<String, ()> {
-        self.parenthesize(expr.op.lexeme, Some(expr.left), Some(expr.right))
+impl ExprVisitor<String, ()> for Printer {
+    fn visit_assign(&mut self, expr: &Assign) -> Result<String, ()> {
+        self.parenthesize(
+            Some(format!("= {}", expr.name.lexeme())),
+            None,
+            Some(&expr.value),
+        )
     }
 
-    fn visit_grouping(&self, expr: Grouping) -> Result<String, ()> {
-        self.parenthesize(Some("group".into()), Some(expr.expr), None)
+    fn visit_binary(&mut self, expr: &Binary) -> Result<String, ()> {
+        self.parenthesize(
+            Some(expr.op.lexeme().into()),
+            Some(&expr.left),
+            Some(&expr.right),
+        )
     }
 
-    fn visit_literal(&self, expr: Literal) -> Result<String, ()> {
-        if let TokenType::Nil = expr.value {
-            return Ok("nil".into());
+    fn visit_call(&mut self, expr: &Call) -> Result<String, ()> {
+        let mut string = format!("(call {}", expr.callee.accept(self)?);
+
+        for arg in expr.args.iter() {
+            string.push(' ');
+            string.push_str(&arg.accept(self)?);
+        }
+
+        string.push(')');
+
+        Ok(string)
+    }
+
+    fn visit_get(&mut self, expr: &Get) -> Result<String, ()> {
+        self.parenthesize(
+            Some(format!(". {}", expr.name.lexeme())),
+            Some(&expr.object),
+            None,
+        )
+    }
+
+    fn visit_grouping(&mut self, expr: &Grouping) -> Result<String, ()> {
+        self.parenthesize(Some("group".into()), Some(&expr.expr), None)
+    }
+
+    fn visit_literal(&mut self, expr: &Literal) -> Result<String, ()> {
+        if let Some(keyword) = expr.value.as_keyword(Dialect::Native) {
+            return Ok(keyword.into());
         }
         Ok(format!("{:?}", expr.value))
     }
 
-    fn visit_unary(&self, expr: Unary) -> Result<String, ()> {
-        self.parenthesize(expr.op.lexeme, None, Some(expr.right))
+    fn visit_logical(&mut self, expr: &Logical) -> Result<String, ()> {
+        self.parenthesize(
+            Some(expr.op.lexeme().into()),
+            Some(&expr.left),
+            Some(&expr.right),
+        )
+    }
+
+    fn visit_set(&mut self, expr: &Set) -> Result<String, ()> {
+        self.parenthesize(
+            Some(format!("= .{}", expr.name.lexeme())),
+            Some(&expr.object),
+            Some(&expr.value),
+        )
+    }
+
+    fn visit_super(&mut self, expr: &Super) -> Result<String, ()> {
+        Ok(format!("(super {})", expr.method.lexeme()))
+    }
+
+    fn visit_this(&mut self, _: &This) -> Result<String, ()> {
+        Ok("this".into())
+    }
+
+    fn visit_unary(&mut self, expr: &Unary) -> Result<String, ()> {
+        self.parenthesize(Some(expr.op.lexeme().into()), None, Some(&expr.right))
+    }
+
+    fn visit_variable(&mut self, expr: &Variable) -> Result<String, ()> {
+        Ok(expr.name.lexeme().into())
     }
 }
 
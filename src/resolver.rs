@@ -0,0 +1,438 @@
+//! Static pass that runs between parsing and interpreting. It binds every local variable
+//! reference to the scope it was declared in, recording the number of hops on the node itself, and
+//! reports scoping mistakes before any code runs.
+
+use std::{cell::Cell, collections::HashMap, convert::Infallible};
+
+use crate::{
+    gen_ast::{
+        Assign, Binary, Block, Call, Class, Expr, ExprVisitor, Expression, Function, Get, Grouping,
+        If, Literal, Logical, Print, Return, Set, Stmt, StmtVisitor, Super, This, Unary, Var,
+        Variable, While,
+    },
+    token::Token,
+};
+
+#[derive(Debug)]
+pub struct ResolveError {
+    pub token: Token,
+    pub msg: String,
+    /// Where the variable the error is about was declared, if that helps explain it.
+    pub declared: Option<Token>,
+    pub help: Option<&'static str>,
+}
+
+#[derive(Debug)]
+struct Local {
+    name: Token,
+    /// Whether the variable's initializer has finished.
+    defined: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionKind {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassKind {
+    None,
+    Class,
+    Subclass,
+}
+
+#[derive(Debug)]
+pub struct Resolver {
+    /// Innermost scope last.
+    scopes: Vec<HashMap<String, Local>>,
+    function: FunctionKind,
+    class: ClassKind,
+    errors: Vec<ResolveError>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self {
+            scopes: vec![],
+            function: FunctionKind::None,
+            class: ClassKind::None,
+            errors: vec![],
+        }
+    }
+}
+
+impl ExprVisitor<(), Infallible> for Resolver {
+    fn visit_assign(&mut self, expr: &Assign) -> Result<(), Infallible> {
+        self.resolve_expr(&expr.value)?;
+        self.resolve_local(&expr.name, &expr.depth);
+        Ok(())
+    }
+
+    fn visit_binary(&mut self, expr: &Binary) -> Result<(), Infallible> {
+        self.resolve_expr(&expr.left)?;
+        self.resolve_expr(&expr.right)
+    }
+
+    fn visit_call(&mut self, expr: &Call) -> Result<(), Infallible> {
+        self.resolve_expr(&expr.callee)?;
+
+        for arg in expr.args.iter() {
+            self.resolve_expr(arg)?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_get(&mut self, expr: &Get) -> Result<(), Infallible> {
+        self.resolve_expr(&expr.object)
+    }
+
+    fn visit_grouping(&mut self, expr: &Grouping) -> Result<(), Infallible> {
+        self.resolve_expr(&expr.expr)
+    }
+
+    fn visit_literal(&mut self, _: &Literal) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn visit_logical(&mut self, expr: &Logical) -> Result<(), Infallible> {
+        self.resolve_expr(&expr.left)?;
+        self.resolve_expr(&expr.right)
+    }
+
+    fn visit_set(&mut self, expr: &Set) -> Result<(), Infallible> {
+        self.resolve_expr(&expr.value)?;
+        self.resolve_expr(&expr.object)
+    }
+
+    fn visit_super(&mut self, expr: &Super) -> Result<(), Infallible> {
+        match self.class {
+            ClassKind::None => {
+                self.error(&expr.keyword, "Can't use 'super' outside of a class.");
+            }
+            ClassKind::Class => {
+                self.error(
+                    &expr.keyword,
+                    "Can't use 'super' in a class with no superclass.",
+                );
+            }
+            ClassKind::Subclass => self.resolve_local(&expr.keyword, &expr.depth),
+        }
+
+        Ok(())
+    }
+
+    fn visit_this(&mut self, expr: &This) -> Result<(), Infallible> {
+        if self.class == ClassKind::None {
+            self.error(&expr.keyword, "Can't use 'this' outside of a class.");
+            return Ok(());
+        }
+
+        self.resolve_local(&expr.keyword, &expr.depth);
+
+        Ok(())
+    }
+
+    fn visit_unary(&mut self, expr: &Unary) -> Result<(), Infallible> {
+        self.resolve_expr(&expr.right)
+    }
+
+    fn visit_variable(&mut self, expr: &Variable) -> Result<(), Infallible> {
+        let declared = self
+            .scopes
+            .last()
+            .and_then(|scope| scope.get(expr.name.lexeme()))
+            .filter(|local| !local.defined)
+            .map(|local| local.name.clone());
+
+        if declared.is_some() {
+            self.error(
+                &expr.name,
+                "Can't read local variable in its own initializer.",
+            )
+            .declared = declared;
+        }
+
+        self.resolve_local(&expr.name, &expr.depth);
+
+        Ok(())
+    }
+}
+
+impl StmtVisitor<(), Infallible> for Resolver {
+    fn visit_block(&mut self, stmt: &Block) -> Result<(), Infallible> {
+        self.begin_scope();
+        self.resolve(&stmt.stmts)?;
+        self.end_scope();
+        Ok(())
+    }
+
+    fn visit_class(&mut self, stmt: &Class) -> Result<(), Infallible> {
+        let enclosing = std::mem::replace(&mut self.class, ClassKind::Class);
+
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+
+        if let Some(superclass) = &stmt.superclass {
+            if superclass.name.lexeme() == stmt.name.lexeme() {
+                self.error(&superclass.name, "A class can't inherit from itself.");
+            }
+
+            self.class = ClassKind::Subclass;
+            self.visit_variable(superclass)?;
+
+            // Methods of a subclass close over an extra scope holding `super`.
+            self.begin_scope();
+
+            self.define_implicit("super", &superclass.name);
+        }
+
+        self.begin_scope();
+
+        self.define_implicit("this", &stmt.name);
+
+        for method in stmt.methods.iter() {
+            let kind = if method.name.lexeme() == "init" {
+                FunctionKind::Initializer
+            } else {
+                FunctionKind::Method
+            };
+
+            self.resolve_function(method, kind)?;
+        }
+
+        self.end_scope();
+
+        if stmt.superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.class = enclosing;
+
+        Ok(())
+    }
+
+    fn visit_expression(&mut self, stmt: &Expression) -> Result<(), Infallible> {
+        self.resolve_expr(&stmt.expr)
+    }
+
+    fn visit_function(&mut self, stmt: &Function) -> Result<(), Infallible> {
+        // Defined eagerly so the function can refer to itself recursively.
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+
+        self.resolve_function(stmt, FunctionKind::Function)
+    }
+
+    fn visit_if(&mut self, stmt: &If) -> Result<(), Infallible> {
+        self.resolve_expr(&stmt.cond)?;
+        self.resolve_stmt(&stmt.then_branch)?;
+
+        if let Some(else_branch) = &stmt.else_branch {
+            self.resolve_stmt(else_branch)?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_print(&mut self, stmt: &Print) -> Result<(), Infallible> {
+        self.resolve_expr(&stmt.expr)
+    }
+
+    fn visit_return(&mut self, stmt: &Return) -> Result<(), Infallible> {
+        if self.function == FunctionKind::None {
+            self.error(&stmt.keyword, "Can't return from top-level code.");
+        }
+
+        if let Some(value) = &stmt.value {
+            if self.function == FunctionKind::Initializer {
+                self.error(&stmt.keyword, "Can't return a value from an initializer.")
+                    .help = Some("initializers always return 'this'; use a bare 'return;'");
+            }
+
+            self.resolve_expr(value)?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_var(&mut self, stmt: &Var) -> Result<(), Infallible> {
+        self.declare(&stmt.name);
+
+        if let Some(initializer) = &stmt.initializer {
+            self.resolve_expr(initializer)?;
+        }
+
+        self.define(&stmt.name);
+
+        Ok(())
+    }
+
+    fn visit_while(&mut self, stmt: &While) -> Result<(), Infallible> {
+        self.resolve_expr(&stmt.cond)?;
+        self.resolve_stmt(&stmt.body)
+    }
+}
+
+impl Resolver {
+    /// Resolves a whole program, collecting every static error instead of stopping at the first.
+    pub fn resolve_program(mut self, stmts: &[Stmt]) -> Result<(), Vec<ResolveError>> {
+        self.resolve(stmts).ok();
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn resolve(&mut self, stmts: &[Stmt]) -> Result<(), Infallible> {
+        stmts.iter().try_for_each(|stmt| self.resolve_stmt(stmt))
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Stmt) -> Result<(), Infallible> {
+        stmt.accept(self)
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) -> Result<(), Infallible> {
+        expr.accept(self)
+    }
+
+    fn resolve_function(&mut self, stmt: &Function, kind: FunctionKind) -> Result<(), Infallible> {
+        let enclosing = std::mem::replace(&mut self.function, kind);
+
+        self.begin_scope();
+
+        for param in stmt.params.iter() {
+            self.declare(param);
+            self.define(param);
+        }
+
+        self.resolve(&stmt.body)?;
+        self.end_scope();
+
+        self.function = enclosing;
+
+        Ok(())
+    }
+
+    /// Records how many scopes away `name` was declared. Anything not found is assumed global and
+    /// left unresolved.
+    fn resolve_local(&mut self, name: &Token, depth: &Cell<Option<usize>>) {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(name.lexeme()));
+
+        depth.set(found);
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        let local = Local {
+            name: name.clone(),
+            defined: false,
+        };
+
+        if let Some(previous) = scope.insert(name.lexeme().into(), local) {
+            self.error(name, "Already a variable with this name in this scope.")
+                .declared = Some(previous.name);
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(local) = self
+            .scopes
+            .last_mut()
+            .and_then(|scope| scope.get_mut(name.lexeme()))
+        {
+            local.defined = true;
+        }
+    }
+
+    /// Binds `this` or `super`, which have no declaration of their own, to the class that
+    /// introduces them.
+    fn define_implicit(&mut self, name: &str, class: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            let local = Local {
+                name: class.clone(),
+                defined: true,
+            };
+            scope.insert(name.into(), local);
+        }
+    }
+
+    /// Records an error and hands it back so the caller can attach extra context to it.
+    fn error(&mut self, token: &Token, msg: &str) -> &mut ResolveError {
+        self.errors.push(ResolveError {
+            token: token.clone(),
+            msg: msg.into(),
+            declared: None,
+            help: None,
+        });
+
+        self.errors.last_mut().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{lexer::Lexer, parser::Parser, resolver::Resolver};
+
+    fn errors(src: &str) -> Vec<String> {
+        let stmts = Parser::new(Lexer::new(src).flatten()).parse().unwrap();
+
+        match Resolver::default().resolve_program(&stmts) {
+            Ok(()) => vec![],
+            Err(errs) => errs.into_iter().map(|e| e.msg).collect(),
+        }
+    }
+
+    #[test]
+    fn check_scope_errors() {
+        assert_eq!(
+            errors("return 1; { var a = 1; var a = 2; } { var b = b; }"),
+            vec![
+                "Can't return from top-level code.",
+                "Already a variable with this name in this scope.",
+                "Can't read local variable in its own initializer.",
+            ]
+        );
+
+        assert_eq!(
+            errors("print this; class A { init() { return 1; } }"),
+            vec![
+                "Can't use 'this' outside of a class.",
+                "Can't return a value from an initializer.",
+            ]
+        );
+
+        assert_eq!(
+            errors("class A < A {} super.x; class B { f() { super.f(); } }"),
+            vec![
+                "A class can't inherit from itself.",
+                "Can't use 'super' outside of a class.",
+                "Can't use 'super' in a class with no superclass.",
+            ]
+        );
+
+        // Globals may be redeclared, functions may return and initializers may bail out early.
+        assert!(errors("var a = 1; var a = 2; fn f() { return a; }").is_empty());
+        assert!(errors("class A { init() { return; } get() { return this; } }").is_empty());
+    }
+}
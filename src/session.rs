@@ -0,0 +1,135 @@
+//! The API for embedding Lox in a Rust program.
+
+use crate::{
+    interpreter::{Interpreter, Output},
+    lox::{Lox, LoxError},
+    token::Token,
+    token_type::{Dialect, TokenType},
+    value::Value,
+};
+
+/// An interpreter whose globals outlive each piece of source it runs, so a host can load a script
+/// once and then read its variables or call its functions.
+#[derive(Debug, Default)]
+pub struct Session {
+    interpreter: Interpreter,
+    dialect: Dialect,
+}
+
+impl Session {
+    /// A session that prints to stdout and reads the native dialect.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends `print` output somewhere other than stdout, e.g. a buffer the host reads back.
+    /// Globals defined so far are kept.
+    pub fn with_output(mut self, output: Output) -> Self {
+        self.interpreter.set_output(output);
+        self
+    }
+
+    /// How many bytes of stack a script may use before failing with a stack overflow, by default
+    /// [`DEFAULT_STACK_LIMIT`](crate::DEFAULT_STACK_LIMIT). Raise it only when running on a thread
+    /// with a larger stack.
+    pub fn with_stack_limit(self, bytes: usize) -> Self {
+        Self {
+            interpreter: self.interpreter.with_stack_limit(bytes),
+            ..self
+        }
+    }
+
+    pub fn with_dialect(self, dialect: Dialect) -> Self {
+        Self { dialect, ..self }
+    }
+
+    /// Runs `src` and returns the value of its trailing expression statement, or `nil` when it
+    /// doesn't end with one.
+    pub fn eval(&mut self, src: &str) -> Result<Value, LoxError> {
+        Ok(Lox::run(&mut self.interpreter, src, self.dialect)?.unwrap_or(Value::Nil))
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.interpreter.get_global(name)
+    }
+
+    pub fn set_global(&mut self, name: &str, value: impl Into<Value>) {
+        self.interpreter.set_global(name, value.into());
+    }
+
+    /// Calls the global function or class `name`. Errors point at a token made up from `name`,
+    /// since there is no call site in any source.
+    pub fn call_function(&mut self, name: &str, args: Vec<Value>) -> Result<Value, LoxError> {
+        let token = Token::new(TokenType::Id, Some(name.into()), 0);
+
+        let callee = self.interpreter.look_up_global(&token)?;
+
+        Ok(self.interpreter.call(&callee, &token, args)?)
+    }
+
+    /// Exposes a Rust function to scripts as a global. See [`Interpreter::define_native`].
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        fun: impl Fn(&mut Interpreter, Vec<Value>) -> Result<Value, String> + 'static,
+    ) {
+        self.interpreter.define_native(name, arity, fun);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{interpreter::Output, lox::LoxError, session::Session, value::Value};
+
+    #[test]
+    fn check_session() {
+        let output = Rc::new(RefCell::new(String::new()));
+        let mut session = Session::new();
+
+        // Switching the output afterwards mustn't lose the global.
+        session.set_global("greeting", "Hi");
+        let mut session = session.with_output(Output::Buffer(Rc::clone(&output)));
+        session.define_native("double", 1, |_, args| match args[0] {
+            Value::Number(n) => Ok(Value::Number(n * 2.0)),
+            _ => Err("Argument must be a number.".into()),
+        });
+
+        let src = "
+            var count = 0;
+            fn greet(name) {
+                count = count + 1;
+                print greeting + \", \" + name;
+                return double(count);
+            }
+        ";
+        assert_eq!(session.eval(src).unwrap(), Value::Nil);
+        assert_eq!(session.eval("count + 1;").unwrap(), Value::Number(1.0));
+
+        let result = session.call_function("greet", vec!["Bob".into()]);
+        assert_eq!(result.unwrap(), Value::Number(2.0));
+        assert_eq!(session.get_global("count"), Some(Value::Number(1.0)));
+        assert_eq!(*output.borrow(), "Hi, Bob\n");
+
+        let err = |result: Result<Value, LoxError>| match result {
+            Err(LoxError::Runtime(err)) => err.msg,
+            other => panic!("expected a runtime error, got {other:?}"),
+        };
+
+        assert_eq!(
+            err(session.call_function("greet", vec![])),
+            "Expected 1 arguments but got 0."
+        );
+        assert_eq!(
+            err(session.call_function("missing", vec![])),
+            "Undefined variable 'missing'."
+        );
+        assert_eq!(
+            err(session.call_function("count", vec![])),
+            "Can only call functions and classes."
+        );
+        assert!(matches!(session.eval("var;"), Err(LoxError::Parse(_))));
+    }
+}
@@ -0,0 +1,63 @@
+//! Keeps deeply nested or deeply recursive scripts from overflowing the host's stack, which would
+//! abort the whole process rather than just fail the script.
+//!
+//! Recursion is bounded by how much stack it has actually used instead of by counting levels: one
+//! level costs anywhere from a few hundred bytes to tens of kilobytes depending on what is nested
+//! and how the crate was built. The parser and the interpreter each check a guard. The resolver
+//! needs none, since it only walks trees the parser managed to build and spends less per level.
+
+use std::hint::black_box;
+
+/// How much stack parsing or running a piece of source may use unless told otherwise. Leaves
+/// plenty of room on a 2 MiB thread, the smallest default a host is likely to run us on.
+///
+/// That is not much: each level of nested parentheses or calls takes the parser around 18 KB in a
+/// debug build, so only about 55 of them fit, against roughly 350 in a release build. Hosts that
+/// need more should run scripts on a thread with a bigger stack and raise the limit to match.
+pub const DEFAULT_STACK_LIMIT: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub struct StackGuard {
+    limit: usize,
+    /// Address near the top of the stack when measuring started.
+    base: Option<usize>,
+}
+
+impl StackGuard {
+    pub fn new(limit: usize) -> Self {
+        Self { limit, base: None }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Starts measuring from the caller's frame, unless a caller further out already did. Returns
+    /// whether this call started it, in which case it should also `stop` it.
+    pub fn start(&mut self) -> bool {
+        let outermost = self.base.is_none();
+
+        if outermost {
+            self.base = Some(stack_pointer());
+        }
+
+        outermost
+    }
+
+    pub fn stop(&mut self) {
+        self.base = None;
+    }
+
+    /// Whether more than `limit` bytes have been used since `start`.
+    pub fn exhausted(&self) -> bool {
+        self.base
+            .is_some_and(|base| base.saturating_sub(stack_pointer()) > self.limit)
+    }
+}
+
+/// Roughly where the top of the stack is. Every platform we run on grows the stack downwards.
+#[inline(always)]
+fn stack_pointer() -> usize {
+    let marker = 0u8;
+    black_box(&marker) as *const u8 as usize
+}
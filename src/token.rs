@@ -1,22 +1,58 @@
-use std::fmt::Display;
+use std::{fmt::Display, rc::Rc};
 
 use crate::token_type::TokenType;
 
+/// Byte range `start..end` into the source a token was scanned from.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub typ: TokenType,
-    pub lexeme: Option<String>,
     pub line: usize,
+    /// 1-based, counted in characters. Zero for tokens that didn't come from the lexer.
+    pub column: usize,
+    pub span: Span,
+    /// The whole source the token was scanned from, kept so diagnostics can quote it long after
+    /// the token was lexed (e.g. a function called from a later REPL line).
+    pub source: Rc<str>,
 }
 
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {:?} {:?}", self.line, self.typ, self.lexeme)
+        write!(
+            f,
+            "{}:{} {:?} {:?}",
+            self.line,
+            self.column,
+            self.typ,
+            self.lexeme()
+        )
     }
 }
 
 impl Token {
+    /// Makes a token that didn't come from scanning. Its lexeme becomes the whole of its source.
     pub fn new(typ: TokenType, lexeme: Option<String>, line: usize) -> Self {
-        Self { typ, lexeme, line }
+        let source: Rc<str> = lexeme.unwrap_or_default().into();
+
+        Self {
+            typ,
+            line,
+            column: 0,
+            span: Span {
+                start: 0,
+                end: source.len(),
+            },
+            source,
+        }
+    }
+
+    /// The source text the token was scanned from. Empty for `Eof`.
+    pub fn lexeme(&self) -> &str {
+        &self.source[self.span.start..self.span.end]
     }
 }
@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
     // SINGLE-CHARACTER TOKENS.
@@ -24,10 +26,14 @@ pub enum TokenType {
     LessEqual,
 
     // LITERALS.
-    Id(String),
+    Id,
     String(String),
     Number(f64),
 
+    // TRIVIA.
+    /// A `///` comment. The parser gathers these up for the declaration that follows.
+    DocComment,
+
     // KEYWORDS.
     And,
     Class,
@@ -48,3 +54,74 @@ pub enum TokenType {
 
     Eof,
 }
+
+/// Every reserved word, with the token it lexes to. Both the lexer and anything that prints source
+/// back out go through this table, so adding a keyword only takes one line.
+pub const KEYWORDS: [(&str, TokenType); 16] = [
+    ("and", TokenType::And),
+    ("class", TokenType::Class),
+    ("else", TokenType::Else),
+    ("false", TokenType::False),
+    ("fn", TokenType::Fn),
+    ("for", TokenType::For),
+    ("if", TokenType::If),
+    ("nil", TokenType::Nil),
+    ("or", TokenType::Or),
+    ("print", TokenType::Print),
+    ("return", TokenType::Return),
+    ("super", TokenType::Super),
+    ("this", TokenType::This),
+    ("true", TokenType::True),
+    ("var", TokenType::Var),
+    ("while", TokenType::While),
+];
+
+/// Which spelling of the language to accept. The two only differ in the function keyword.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Dialect {
+    /// Ours, which declares functions with `fn`.
+    #[default]
+    Native,
+    /// The book's, which uses `fun`, so upstream test suites and other Lox programs run unmodified.
+    Reference,
+}
+
+impl Dialect {
+    /// How this dialect spells a keyword from `KEYWORDS`.
+    fn spell(self, keyword: &'static str) -> &'static str {
+        match (self, keyword) {
+            (Self::Reference, "fn") => "fun",
+            _ => keyword,
+        }
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "native" => Ok(Self::Native),
+            "reference" => Ok(Self::Reference),
+            _ => Err(format!("Unknown dialect '{s}'.")),
+        }
+    }
+}
+
+impl TokenType {
+    /// The keyword spelled `name` in `dialect`, if it is one.
+    pub fn keyword(name: &str, dialect: Dialect) -> Option<Self> {
+        KEYWORDS
+            .iter()
+            .find(|(kw, _)| dialect.spell(kw) == name)
+            .map(|(_, ty)| ty.clone())
+    }
+
+    /// How this token is spelled in `dialect`, if it is a keyword.
+    pub fn as_keyword(&self, dialect: Dialect) -> Option<&'static str> {
+        KEYWORDS
+            .iter()
+            .find(|(_, ty)| ty == self)
+            .map(|(kw, _)| dialect.spell(kw))
+    }
+}
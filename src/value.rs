@@ -0,0 +1,98 @@
+use std::{cell::RefCell, fmt::Display, rc::Rc};
+
+use crate::{
+    callable::Callable,
+    class::{LoxClass, LoxInstance},
+    function::{LoxFunction, NativeFunction},
+    token_type::TokenType,
+};
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Function(Rc<LoxFunction>),
+    NativeFunction(Rc<NativeFunction>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+}
+
+impl Value {
+    pub fn as_callable(&self) -> Option<&dyn Callable> {
+        match self {
+            Self::Function(f) => Some(f.as_ref()),
+            Self::NativeFunction(f) => Some(f.as_ref()),
+            Self::Class(c) => Some(c),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Nil, Self::Nil) => true,
+            (Self::Bool(l), Self::Bool(r)) => l == r,
+            (Self::Number(l), Self::Number(r)) => l == r,
+            (Self::String(l), Self::String(r)) => l == r,
+            (Self::Function(l), Self::Function(r)) => Rc::ptr_eq(l, r),
+            (Self::NativeFunction(l), Self::NativeFunction(r)) => Rc::ptr_eq(l, r),
+            (Self::Class(l), Self::Class(r)) => Rc::ptr_eq(l, r),
+            (Self::Instance(l), Self::Instance(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Nil => write!(f, "nil"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::String(s) => write!(f, "{s}"),
+            Self::Function(fun) => write!(f, "<fn {}>", fun.name()),
+            Self::NativeFunction(_) => write!(f, "<native fn>"),
+            Self::Class(c) => write!(f, "{}", c.name()),
+            Self::Instance(i) => write!(f, "{} instance", i.borrow().class().name()),
+        }
+    }
+}
+
+impl From<TokenType> for Value {
+    fn from(tty: TokenType) -> Self {
+        match tty {
+            TokenType::True => Self::Bool(true),
+            TokenType::False => Self::Bool(false),
+            TokenType::Number(n) => Self::Number(n),
+            TokenType::String(s) => Self::String(s),
+            _ => Self::Nil,
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Self::Number(n)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Self::String(s.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Self::String(s)
+    }
+}
@@ -0,0 +1,48 @@
+//! Runs the interpreter binary over the `.lox` programs in the repo, so `cargo test` covers them.
+
+use std::process::{Command, Output};
+
+fn lox(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_crafting-interpreters"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn check_programs() {
+    let runs: [&[&str]; 3] = [
+        &["test", "tests/lox"],
+        &["test", "src/corpus"],
+        &["--dialect=reference", "test", "tests/reference"],
+    ];
+
+    for args in runs {
+        let out = lox(args);
+
+        assert!(
+            out.status.success(),
+            "{args:?}:\n{}",
+            String::from_utf8_lossy(&out.stdout)
+        );
+    }
+}
+
+#[test]
+fn check_exit_codes() {
+    let cases = [
+        ("tests/lox/closures.lox", 0),
+        ("tests/lox/lex_error.lox", 65),
+        ("tests/lox/static_errors.lox", 65),
+        ("tests/lox/resolve_errors.lox", 65),
+        ("tests/lox/runtime_error.lox", 70),
+        ("tests/reference/functions.lox", 65),
+    ];
+
+    for (file, code) in cases {
+        assert_eq!(lox(&[file]).status.code(), Some(code), "{file}");
+    }
+
+    assert_eq!(lox(&["--dialect=klingon"]).status.code(), Some(64));
+}